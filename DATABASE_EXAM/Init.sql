@@ -23,6 +23,13 @@ CREATE TABLE IF NOT EXISTS weapon_inventory (
     FOREIGN KEY (weapon_id) REFERENCES weapon(id)
 );
 
+CREATE TABLE IF NOT EXISTS inventory_item (
+    inventory_id UUID,
+    item VARCHAR(50) NOT NULL,
+    count INT NOT NULL,
+    FOREIGN KEY (inventory_id) REFERENCES inventory(id)
+);
+
 CREATE TABLE IF NOT EXISTS player (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     name VARCHAR(100) NOT NULL UNIQUE,
@@ -30,16 +37,62 @@ CREATE TABLE IF NOT EXISTS player (
     max_hp INT NOT NULL,
     defense INT NOT NULL,
     strength INT NOT NULL,
+    level INT NOT NULL DEFAULT 1,
     inventory_id UUID,
     FOREIGN KEY (inventory_id) REFERENCES inventory(id)
 );
 
+-- Databases created before levelling up
+ALTER TABLE player ADD COLUMN IF NOT EXISTS level INT NOT NULL DEFAULT 1;
+
+-- Mirrors assets/bestiary.ron, loot tables only live in the asset
 CREATE TABLE IF NOT EXISTS monster (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-    name VARCHAR(100) NOT NULL,
-    health INT NOT NULL
+    name VARCHAR(100) NOT NULL UNIQUE,
+    glyph CHAR(1) NOT NULL,
+    color VARCHAR(20) NOT NULL,
+    speed INT NOT NULL,
+    health INT NOT NULL,
+    defense INT NOT NULL,
+    strength INT NOT NULL,
+    dice VARCHAR(20) NOT NULL,
+    view_range INT NOT NULL,
+    xp INT NOT NULL,
+    opens_doors BOOLEAN NOT NULL DEFAULT TRUE,
+    spawn_weights INT[] NOT NULL
 );
 
+-- Databases created when monsters only had a name and health, the seed
+-- below fills in the real values
+ALTER TABLE monster ADD COLUMN IF NOT EXISTS glyph CHAR(1) NOT NULL DEFAULT '?';
+ALTER TABLE monster ADD COLUMN IF NOT EXISTS color VARCHAR(20) NOT NULL DEFAULT 'White';
+ALTER TABLE monster ADD COLUMN IF NOT EXISTS speed INT NOT NULL DEFAULT 10;
+ALTER TABLE monster ADD COLUMN IF NOT EXISTS defense INT NOT NULL DEFAULT 0;
+ALTER TABLE monster ADD COLUMN IF NOT EXISTS strength INT NOT NULL DEFAULT 1;
+ALTER TABLE monster ADD COLUMN IF NOT EXISTS dice VARCHAR(20) NOT NULL DEFAULT '1d4';
+ALTER TABLE monster ADD COLUMN IF NOT EXISTS view_range INT NOT NULL DEFAULT 4;
+ALTER TABLE monster ADD COLUMN IF NOT EXISTS xp INT NOT NULL DEFAULT 0;
+ALTER TABLE monster ADD COLUMN IF NOT EXISTS opens_doors BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE monster ADD COLUMN IF NOT EXISTS spawn_weights INT[] NOT NULL DEFAULT '{}';
+-- Same name as the index behind UNIQUE above, so it's only made for old tables
+CREATE UNIQUE INDEX IF NOT EXISTS monster_name_key ON monster (name);
+
+INSERT INTO monster (name, glyph, color, speed, health, defense, strength, dice, view_range, xp, opens_doors, spawn_weights) VALUES
+    ('Goblin', 'g', 'Red', 20, 15, 0, 1, '1d4', 4, 10, TRUE, '{3,3,2,2,1}'),
+    ('Orc', 'o', 'Red', 15, 25, 1, 3, '2d6', 4, 25, FALSE, '{1,1,2,2,3}')
+ON CONFLICT (name) DO UPDATE SET
+    glyph = EXCLUDED.glyph,
+    color = EXCLUDED.color,
+    speed = EXCLUDED.speed,
+    health = EXCLUDED.health,
+    defense = EXCLUDED.defense,
+    strength = EXCLUDED.strength,
+    dice = EXCLUDED.dice,
+    view_range = EXCLUDED.view_range,
+    xp = EXCLUDED.xp,
+    opens_doors = EXCLUDED.opens_doors,
+    spawn_weights = EXCLUDED.spawn_weights;
+
 CREATE TABLE IF NOT EXISTS world (
     id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
     lore_id UUID NOT NULL,
@@ -54,8 +107,32 @@ CREATE TABLE IF NOT EXISTS world_monster (
     FOREIGN KEY (monster_id) REFERENCES monster(id)
 );
 
+CREATE TABLE IF NOT EXISTS run_summary (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    player_name VARCHAR(100) NOT NULL,
+    outcome VARCHAR(10) NOT NULL,
+    turns INT NOT NULL,
+    floor INT NOT NULL,
+    damage_dealt INT NOT NULL,
+    damage_taken INT NOT NULL,
+    weapon VARCHAR(100) NOT NULL,
+    seed VARCHAR(20) NOT NULL,
+    practice BOOLEAN NOT NULL,
+    finished_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS run_kill (
+    run_id UUID,
+    monster VARCHAR(100) NOT NULL,
+    count INT NOT NULL,
+    FOREIGN KEY (run_id) REFERENCES run_summary(id)
+);
+
 -- Functions
 
+-- The returned columns changed, which CREATE OR REPLACE can't do
+DROP FUNCTION IF EXISTS get_player_stats(TEXT);
+
 CREATE OR REPLACE FUNCTION get_player_stats(p_name TEXT)
 RETURNS TABLE (
     player_name VARCHAR(100),
@@ -63,6 +140,7 @@ RETURNS TABLE (
     player_max_hp INT,
     player_defense INT,
     player_strength INT,
+    player_level INT,
     inventory_gold INT,
     weapon_name VARCHAR(100),
     weapon_damage INT,
@@ -80,6 +158,7 @@ BEGIN
         pl.max_hp,
         pl.defense,
         pl.strength,
+        pl.level,
         i.gold,
         w.name,
         w.damage,
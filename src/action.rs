@@ -0,0 +1,279 @@
+use bevy::prelude::*;
+use sark_grids::SizedGrid;
+
+use crate::{
+    combat::{ActorEffect, TargetEvent},
+    dungeon::{ChangeFloorEvent, FloorChange},
+    equipment::{ChangeEquipmentEvent, Equipment, EquipmentChange, Slot},
+    item::{Inventory, Item, ItemKind, PickUpEvent, UseItemEvent},
+    map::{Map, TileProperties},
+    map_state::{MapActors, MapObstacles},
+    monster::OpensDoors,
+    movement::{Movement, Position},
+    player::Player,
+    status::{StatusEffects, StatusKind},
+    turn_system::{turns_running, Energy, TakingATurn, TurnBeginSet, TurnEndSet, ACTION_COST},
+};
+use crate::PathMap2dExt::PathMap2dExt;
+
+/// Plugin resolving the [Action]s chosen by the player and monsters.
+pub struct ActionPlugin;
+
+/// Systems choosing actions run before this set, the turn ends after it.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ResolveActionsSet;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ActionEvent>()
+            .add_event::<ActionResolvedEvent>()
+            .configure_sets(
+                Update,
+                ResolveActionsSet
+                    .after(TurnBeginSet)
+                    .before(TurnEndSet)
+                    .run_if(turns_running),
+            )
+            .add_systems(Update, resolve_actions_system.in_set(ResolveActionsSet));
+    }
+}
+
+/// Something an actor does with its turn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Wait,
+    /// Step in a direction. Bumping into a door opens it, bumping into an
+    /// enemy attacks it.
+    Move(IVec2),
+    Attack(Entity),
+    /// Take the stairs the actor is standing on.
+    UseStairs(FloorChange),
+    /// Pick up the item the actor is standing on.
+    PickUp,
+    /// Use up a carried item, or put it on if it's worn.
+    UseItem(ItemKind),
+    /// Put the armor or trinket in a slot back in the pack.
+    TakeOff(Slot),
+    /// Switch to another carried weapon, see [Equipment::weapons].
+    Wield(usize),
+}
+
+/// An actor that is [TakingATurn] wants to perform an [Action].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ActionEvent {
+    pub actor: Entity,
+    pub action: Action,
+}
+
+/// An [Action] passed validation and was carried out.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ActionResolvedEvent {
+    pub actor: Entity,
+    pub action: Action,
+    /// Where the actor stood when it acted.
+    pub from: IVec2,
+}
+
+/// Why an [Action] couldn't be carried out.
+#[derive(Debug, PartialEq, Eq)]
+enum Rejected {
+    Blocked,
+    NotHostile,
+    /// The target isn't standing next to the actor.
+    OutOfReach,
+    NoStairs,
+    NothingToPickUp,
+    CantCarry,
+    InventoryFull,
+    NotCarried,
+    NothingToTakeOff,
+    AlreadyWielded,
+}
+
+/// Everything an actor needs to act, grouped to keep the resolver readable.
+type ActorQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Position,
+        &'static mut Energy,
+        &'static mut Movement,
+        Option<&'static OpensDoors>,
+        Option<&'static StatusEffects>,
+        Option<&'static Inventory>,
+        Option<&'static Equipment>,
+    ),
+    With<TakingATurn>,
+>;
+
+#[allow(clippy::too_many_arguments)]
+fn resolve_actions_system(
+    mut evt_action: EventReader<ActionEvent>,
+    mut evt_resolved: EventWriter<ActionResolvedEvent>,
+    mut evt_target: EventWriter<TargetEvent>,
+    mut evt_floor: EventWriter<ChangeFloorEvent>,
+    mut evt_pick_up: EventWriter<PickUpEvent>,
+    mut evt_use: EventWriter<UseItemEvent>,
+    mut evt_equipment: EventWriter<ChangeEquipmentEvent>,
+    mut q_actors: ActorQuery,
+    q_items: Query<(Entity, &Position), (With<Item>, Without<TakingATurn>)>,
+    q_targets: Query<&Position, Without<TakingATurn>>,
+    q_player: Query<(), With<Player>>,
+    mut q_map: Query<&mut Map>,
+    mut obstacles: ResMut<MapObstacles>,
+    mut actors: ResMut<MapActors>,
+) {
+    let Ok(mut map) = q_map.single_mut() else {
+        return;
+    };
+
+    for ev in evt_action.read() {
+        let is_player = q_player.contains(ev.actor);
+        let Ok((mut pos, mut energy, mut movement, opens_doors, effects, inventory, equipment)) = q_actors.get_mut(ev.actor) else {
+            debug!("{:?} tried {:?} outside of its turn", ev.actor, ev.action);
+            continue;
+        };
+        let from = pos.0;
+
+        // Stunned actors lose their turn whatever they try
+        let action = if effects.is_some_and(|e| e.has(StatusKind::Stun)) {
+            Action::Wait
+        } else {
+            ev.action
+        };
+
+        let mut attack = |target: Entity| {
+            if is_player == q_player.contains(target) {
+                return Err(Rejected::NotHostile);
+            }
+            // Attacks only reach the eight surrounding tiles
+            match q_targets.get(target) {
+                Ok(p) if (p.0 - from).abs().max_element() == 1 => {}
+                _ => return Err(Rejected::OutOfReach),
+            }
+            evt_target.write(TargetEvent {
+                actor: ev.actor,
+                target,
+                effect: ActorEffect::Attack,
+            });
+            Ok(ACTION_COST)
+        };
+
+        let mut change_equipment = |change: EquipmentChange| {
+            evt_equipment.write(ChangeEquipmentEvent {
+                actor: ev.actor,
+                change,
+            });
+            Ok(ACTION_COST)
+        };
+
+        let result = match action {
+            Action::Wait => Ok(ACTION_COST),
+            Action::Attack(target) => attack(target),
+            Action::UseStairs(change) => {
+                if map.0[pos.0] == change.stairs() {
+                    evt_floor.write(ChangeFloorEvent(change));
+                    Ok(ACTION_COST)
+                } else {
+                    Err(Rejected::NoStairs)
+                }
+            }
+            Action::PickUp => match (inventory, q_items.iter().find(|(_, p)| p.0 == pos.0)) {
+                (_, None) => Err(Rejected::NothingToPickUp),
+                (None, _) => Err(Rejected::CantCarry),
+                (Some(inventory), _) if inventory.is_full() => Err(Rejected::InventoryFull),
+                (Some(_), Some((item, _))) => {
+                    evt_pick_up.write(PickUpEvent {
+                        actor: ev.actor,
+                        item,
+                    });
+                    Ok(ACTION_COST)
+                }
+            },
+            Action::UseItem(kind) => match (inventory, equipment) {
+                (Some(i), _) if !i.0.contains(&kind) => Err(Rejected::NotCarried),
+                (None, _) => Err(Rejected::NotCarried),
+                (Some(_), Some(_)) if kind.slot().is_some() => change_equipment(EquipmentChange::Wear(kind)),
+                (Some(_), None) if kind.slot().is_some() => Err(Rejected::CantCarry),
+                (Some(_), _) => {
+                    evt_use.write(UseItemEvent {
+                        actor: ev.actor,
+                        kind,
+                    });
+                    Ok(ACTION_COST)
+                }
+            },
+            Action::TakeOff(slot) => match (inventory, equipment) {
+                (Some(_), Some(e)) if slot == Slot::Weapon || e.is_empty(slot) => Err(Rejected::NothingToTakeOff),
+                (Some(i), Some(_)) if i.is_full() => Err(Rejected::InventoryFull),
+                (Some(_), Some(_)) => change_equipment(EquipmentChange::TakeOff(slot)),
+                _ => Err(Rejected::CantCarry),
+            },
+            Action::Wield(index) => match equipment {
+                None => Err(Rejected::CantCarry),
+                Some(e) if index >= e.weapons.len() => Err(Rejected::NotCarried),
+                Some(e) if e.wielded == Some(index) => Err(Rejected::AlreadyWielded),
+                Some(_) => change_equipment(EquipmentChange::Wield(index)),
+            },
+            Action::Move(dir) => {
+                let curr = pos.0;
+                let next = curr + dir;
+                let tile = if map.0.in_bounds(next) {
+                    Some(map.0[next].properties())
+                } else {
+                    None
+                };
+                let grid = obstacles.0.grid_mut();
+
+                match tile {
+                    None => Err(Rejected::Blocked),
+                    Some(TileProperties { opens_into: Some(opened), .. }) => {
+                        if opens_doors.is_none_or(|o| o.0) {
+                            map.0[next] = opened;
+                            grid[next] = opened.properties().blocks_movement;
+                            Ok(ACTION_COST)
+                        } else {
+                            Err(Rejected::Blocked)
+                        }
+                    }
+                    Some(_) if grid[next] => match actors.0[next] {
+                        Some(target) => attack(target),
+                        None => Err(Rejected::Blocked),
+                    },
+                    Some(tile) => {
+                        pos.0 = next;
+                        actors.0[curr] = None;
+                        actors.0[next] = Some(ev.actor);
+                        grid[curr] = false;
+                        grid[next] = true;
+                        movement.0 = dir;
+                        // Wading through water and the like eats into the next turn
+                        Ok(ACTION_COST + tile.move_cost)
+                    }
+                }
+            }
+        };
+
+        match result {
+            Ok(cost) => {
+                energy.spend(cost);
+                evt_resolved.write(ActionResolvedEvent {
+                    actor: ev.actor,
+                    action,
+                    from,
+                });
+            }
+            // The player just picks something else, monsters would stall the turn queue
+            Err(reason) if !is_player => {
+                debug!("{:?} can't {:?}: {:?}, waiting instead", ev.actor, ev.action, reason);
+                energy.spend(ACTION_COST);
+                evt_resolved.write(ActionResolvedEvent {
+                    actor: ev.actor,
+                    action: Action::Wait,
+                    from,
+                });
+            }
+            Err(reason) => debug!("Player can't {:?}: {:?}", ev.action, reason),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! The monsters the generator can spawn, loaded from [BESTIARY_FILE_NAME].
+
+use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_ascii_terminal::color;
+use rand::Rng;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::loot::LootTable;
+
+/// Path of the bestiary, relative to the `assets` folder.
+pub const BESTIARY_FILE_NAME: &str = "bestiary.ron";
+
+/// The bestiary shipped with the game, used until the asset is loaded.
+const BUILTIN_BESTIARY: &str = include_str!("../assets/bestiary.ron");
+
+/// The terminal colors a monster can be drawn with.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MonsterColor {
+    White,
+    Gray,
+    LightGray,
+    DarkGray,
+    Yellow,
+    Green,
+    LightGreen,
+    Red,
+    DarkRed,
+    Cyan,
+    DarkOrange,
+}
+
+impl MonsterColor {
+    pub fn color(&self) -> Color {
+        match self {
+            MonsterColor::White => Color::from(color::WHITE),
+            MonsterColor::Gray => Color::from(color::GRAY),
+            MonsterColor::LightGray => Color::from(color::LIGHT_GRAY),
+            MonsterColor::DarkGray => Color::from(color::DARK_GRAY),
+            MonsterColor::Yellow => Color::from(color::YELLOW),
+            MonsterColor::Green => Color::from(color::GREEN),
+            MonsterColor::LightGreen => Color::from(color::LIGHT_GREEN),
+            MonsterColor::Red => Color::from(color::RED),
+            MonsterColor::DarkRed => Color::from(color::DARK_RED),
+            MonsterColor::Cyan => Color::from(color::CYAN),
+            MonsterColor::DarkOrange => Color::from(color::DARK_ORANGE),
+        }
+    }
+}
+
+/// One species, as written in the bestiary.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MonsterTemplate {
+    pub name: String,
+    pub glyph: char,
+    pub color: MonsterColor,
+    pub speed: i32,
+    pub hp: i32,
+    pub defense: i32,
+    pub strength: i32,
+    /// Attack dice as `(count, sides, bonus)`.
+    pub dice: (i32, i32, i32),
+    pub view_range: u32,
+    pub xp: i32,
+    /// Whether the monster can open closed doors on its way to the player.
+    #[serde(default = "default_opens_doors")]
+    pub opens_doors: bool,
+    #[serde(default)]
+    pub loot: LootTable,
+    /// Spawn weight per floor, starting at the entrance. Deeper floors use the last one.
+    pub spawn_weights: Vec<u32>,
+}
+
+fn default_opens_doors() -> bool {
+    true
+}
+
+impl MonsterTemplate {
+    pub fn spawn_weight(&self, depth: u32) -> u32 {
+        let depth = (depth as usize).min(self.spawn_weights.len().saturating_sub(1));
+        self.spawn_weights.get(depth).copied().unwrap_or(0)
+    }
+}
+
+#[derive(Asset, TypePath, Debug, Deserialize, Clone)]
+pub struct Bestiary {
+    pub monsters: Vec<MonsterTemplate>,
+}
+
+impl Default for Bestiary {
+    fn default() -> Self {
+        ron::de::from_str(BUILTIN_BESTIARY).expect("the built-in bestiary should parse")
+    }
+}
+
+impl Bestiary {
+    /// Looks up a monster by name, used to respawn monsters on revisited floors.
+    pub fn get(&self, name: &str) -> Option<&MonsterTemplate> {
+        self.monsters.iter().find(|monster| monster.name == name)
+    }
+
+    /// Looks up a monster by the glyph it's drawn with, used by vault templates.
+    pub fn by_glyph(&self, glyph: char) -> Option<&MonsterTemplate> {
+        self.monsters.iter().find(|monster| monster.glyph == glyph)
+    }
+
+    /// Picks a monster for a floor at `depth` by spawn weight, `None` if nothing spawns there.
+    pub fn pick(&self, rng: &mut impl Rng, depth: u32) -> Option<&MonsterTemplate> {
+        let total: u32 = self.monsters.iter().map(|m| m.spawn_weight(depth)).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = rng.random_range(0..total);
+        for monster in self.monsters.iter() {
+            let weight = monster.spawn_weight(depth);
+            if roll < weight {
+                return Some(monster);
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
+
+/// Handle to the [Bestiary] loaded from [BESTIARY_FILE_NAME].
+#[derive(Resource)]
+pub struct BestiaryHandle(pub Handle<Bestiary>);
+
+#[derive(Debug, Error)]
+pub enum BestiaryLoaderError {
+    #[error("Error reading {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Error parsing {path}: {source}")]
+    Parse {
+        path: String,
+        #[source]
+        source: ron::error::SpannedError,
+    },
+}
+
+/// Loads the [Bestiary] from RON files.
+#[derive(Default)]
+pub struct BestiaryLoader;
+
+impl AssetLoader for BestiaryLoader {
+    type Asset = Bestiary;
+    type Settings = ();
+    type Error = BestiaryLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let path = load_context.path().display().to_string();
+
+        let mut bytes = Vec::new();
+        if let Err(source) = reader.read_to_end(&mut bytes).await {
+            return Err(BestiaryLoaderError::Io { path, source });
+        }
+
+        ron::de::from_bytes::<Bestiary>(&bytes)
+            .map_err(|source| BestiaryLoaderError::Parse { path, source })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+pub fn load_bestiary(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load::<Bestiary>(BESTIARY_FILE_NAME);
+    commands.insert_resource(BestiaryHandle(handle));
+}
+
+pub fn log_bestiary_errors(mut evt_failed: EventReader<AssetLoadFailedEvent<Bestiary>>) {
+    for ev in evt_failed.read() {
+        error!("{} could not be loaded, using the built-in bestiary: {}", ev.path, ev.error);
+    }
+}
+
+/// The loaded [Bestiary], falling back to the built-in one.
+#[derive(SystemParam)]
+pub struct BestiaryAssets<'w> {
+    handle: Option<Res<'w, BestiaryHandle>>,
+    bestiaries: Res<'w, Assets<Bestiary>>,
+}
+
+impl BestiaryAssets<'_> {
+    pub fn get(&self) -> Bestiary {
+        match self.handle.as_ref().and_then(|h| self.bestiaries.get(&h.0)) {
+            Some(bestiary) => bestiary.clone(),
+            None => {
+                warn!("{} is not loaded, using the built-in bestiary", BESTIARY_FILE_NAME);
+                Bestiary::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::Bestiary;
+
+    #[test]
+    fn bestiary_picks_by_depth_weight() {
+        let mut bestiary = Bestiary::default();
+        assert!(bestiary.by_glyph('g').is_some());
+        assert!(bestiary.monsters.iter().any(|monster| !monster.opens_doors));
+
+        for monster in bestiary.monsters.iter_mut() {
+            monster.spawn_weights = if monster.name == "Orc" { vec![0, 1] } else { vec![1, 0] };
+        }
+
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..20 {
+            assert_eq!("Goblin", bestiary.pick(&mut rng, 0).unwrap().name);
+            assert_eq!("Orc", bestiary.pick(&mut rng, 5).unwrap().name);
+        }
+
+        bestiary.monsters.clear();
+        assert!(bestiary.pick(&mut rng, 0).is_none());
+    }
+}
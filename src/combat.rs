@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bracket_random::prelude::DiceType;
 use sark_grids::Grid;
 use sark_pathfinding::PathMap2d;
-use crate::{ui::PrintLog, map_state::{MapObstacles, MapActors}, movement::Position, AppState};
+use crate::{rng::CombatRng, status::{OnHitStatus, StatusEffects, StatusKind}, ui::PrintLog, map_state::{MapObstacles, MapActors}, movement::Position, player::Player, experience::XpReward, loot::{DropLootEvent, LootTable}, AppState, GamePhase};
 use bevy::app::PostUpdate;
 
 
@@ -17,6 +17,7 @@ impl Plugin for CombatPlugin {
         app
             .add_event::<TargetEvent>()
             .add_event::<ActorKilledEvent>()
+            .add_event::<DamageEvent>()
             .configure_sets(
                 PostUpdate, 
                 ResolveTargetEventsSet.run_if(in_state(AppState::InGame)),
@@ -54,11 +55,47 @@ pub struct CombatantBundle {
     pub defense: Defense,
     pub strength: Strength,
     pub attack_dice: AttackDice,
+    pub status: StatusEffects,
 }
 
 pub enum ActorEffect {
     Heal(i32),
+    /// Damage that isn't an attack, reduced by the target's defense.
     Damage(i32),
+    /// A melee attack with the actor's [AttackDice], rolled to hit against the target's defense.
+    Attack,
+    /// A turn of a status effect, damage or healing that ignores defense.
+    Status(StatusKind, i32),
+    /// Moves the target to a random free floor tile, resolved by [crate::item].
+    Teleport,
+    /// Fills in the target's map memory with the whole floor, resolved by [crate::item].
+    RevealMap,
+}
+
+/// A d20 roll, plus half the attacker's strength, has to reach this plus the
+/// target's defense to hit.
+pub const HIT_TARGET: i32 = 10;
+
+/// How an attack roll turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttackOutcome {
+    /// A natural 1, always misses.
+    Fumble,
+    Miss,
+    Hit,
+    /// A natural 20, always hits and rolls damage twice.
+    Critical,
+}
+
+impl AttackOutcome {
+    pub fn from_roll(natural: i32, strength: i32, defense: i32) -> Self {
+        match natural {
+            1 => AttackOutcome::Fumble,
+            20 => AttackOutcome::Critical,
+            n if n + strength / 2 >= HIT_TARGET + defense => AttackOutcome::Hit,
+            _ => AttackOutcome::Miss,
+        }
+    }
 }
 #[derive(Event)]
 pub struct TargetEvent {
@@ -69,15 +106,32 @@ pub struct TargetEvent {
 
 #[derive(Event)]
 pub struct ActorKilledEvent {
-    name: String,
+    pub name: String,
+    /// The player died, not a monster.
+    pub player: bool,
+    /// Experience awarded for the kill.
+    pub xp: i32,
 }
 
+/// Damage that got through the target's defense.
+#[derive(Event)]
+pub struct DamageEvent {
+    pub actor: Entity,
+    pub target: Entity,
+    pub amount: i32,
+}
+
+#[allow(clippy::too_many_arguments)]
 fn resolve_target_events(
     q_names: Query<&Name>,
-    q_attack: Query<&mut Strength>,
+    q_attack: Query<(&AttackDice, &Strength)>,
     mut q_defend: Query<(&mut HitPoints, &MaxHitPoints, &Defense)>,
+    q_on_hit: Query<&OnHitStatus>,
+    mut q_status: Query<&mut StatusEffects>,
     mut log: ResMut<PrintLog>,
     mut target_events: EventReader<TargetEvent>,
+    mut evt_damage: EventWriter<DamageEvent>,
+    mut rng: ResMut<CombatRng>,
 ) {
     for ev in target_events.read() {
         let tar = ev.target;
@@ -94,13 +148,90 @@ fn resolve_target_events(
                     // TODO: Move this into ui? No reason to handle it here, would make it simpler + cleaner
                     if let Ok(actor_name) = q_names.get(actor) {
                         if let Ok(target_name) = q_names.get(tar) {
-                            log.push(format!("{} heals {} for {} damage.", actor_name.as_str(), target_name.as_str(), amount));
+                            if actor == tar {
+                                log.push(format!("{} recovers {} HP.", target_name.as_str(), amount));
+                            } else {
+                                log.push(format!("{} heals {} for {} damage.", actor_name.as_str(), target_name.as_str(), amount));
+                            }
                         }
                     }
                 }
             },
+            ActorEffect::Attack => {
+                let (Ok((dice, strength)), Ok((mut hp, _, def))) = (q_attack.get(actor), q_defend.get_mut(tar)) else {
+                    continue;
+                };
+                let actor_name = q_names.get(actor).map_or("Someone", |n| n.as_str());
+                let target_name = q_names.get(tar).map_or("someone", |n| n.as_str());
+
+                let outcome = AttackOutcome::from_roll(rng.roll(DiceType::new(1, 20, 0)), strength.0, def.0);
+                let amount = match outcome {
+                    AttackOutcome::Fumble => {
+                        log.push(format!("{} fumbles the attack on {}.", actor_name, target_name));
+                        continue;
+                    }
+                    AttackOutcome::Miss => {
+                        log.push(format!("{} misses {}.", actor_name, target_name));
+                        continue;
+                    }
+                    AttackOutcome::Hit => rng.roll(dice.0) + strength.0,
+                    AttackOutcome::Critical => rng.roll(dice.0) + rng.roll(dice.0) + strength.0,
+                };
+                hp.0 -= amount;
+                evt_damage.write(DamageEvent {
+                    actor,
+                    target: tar,
+                    amount,
+                });
+
+                if outcome == AttackOutcome::Critical {
+                    log.push(format!("{} lands a critical hit on {} for {} damage!", actor_name, target_name, amount));
+                } else {
+                    log.push(format!("{} attacks {} for {} damage.", actor_name, target_name, amount));
+                }
+
+                if let Ok(on_hit) = q_on_hit.get(actor) {
+                    if rng.roll(DiceType::new(1, 100, 0)) <= on_hit.chance {
+                        let kind = on_hit.effect.kind;
+                        let (receiver, receiver_name) = if kind.is_beneficial() {
+                            (actor, actor_name)
+                        } else {
+                            (tar, target_name)
+                        };
+                        if let Ok(mut effects) = q_status.get_mut(receiver) {
+                            effects.apply(on_hit.effect);
+                            log.push(format!("{} {}!", receiver_name, kind.applied()));
+                        }
+                    }
+                }
+            },
+            ActorEffect::Status(kind, amount) => {
+                let Ok((mut hp, max, _)) = q_defend.get_mut(tar) else {
+                    continue;
+                };
+                let target_name = q_names.get(tar).map_or("Someone", |n| n.as_str());
+
+                if kind.is_beneficial() {
+                    let amount = i32::min(amount, max.0 - hp.0);
+                    if amount <= 0 {
+                        continue;
+                    }
+                    hp.0 += amount;
+                    log.push(format!("{} regains {} HP from {}.", target_name, amount, kind.name()));
+                } else {
+                    hp.0 -= amount;
+                    evt_damage.write(DamageEvent {
+                        actor,
+                        target: tar,
+                        amount,
+                    });
+                    log.push(format!("{} takes {} {} damage.", target_name, amount, kind.name()));
+                }
+            },
+            // Not about hit points, see item::item_effect_system
+            ActorEffect::Teleport | ActorEffect::RevealMap => {}
             ActorEffect::Damage(amount) => {
-                if let Ok(_attack) = q_attack.get(actor) {
+                if q_attack.contains(actor) {
                     if let Ok((mut hp, _, def)) = q_defend.get_mut(tar) {
                         let amount = amount - def.0;
 
@@ -108,6 +239,11 @@ fn resolve_target_events(
                             continue;
                         }
                         hp.0 -= amount;
+                        evt_damage.write(DamageEvent {
+                            actor,
+                            target: tar,
+                            amount,
+                        });
 
                     // TODO: Move this into ui? No reason to handle it here, would make it simpler + cleaner
                         if let Ok(actor_name) = q_names.get(actor) {
@@ -128,12 +264,22 @@ fn death_system(
     mut log: ResMut<PrintLog>,
     mut obstacles: ResMut<MapObstacles>,
     mut blockers: ResMut<MapActors>,
-    q_combatants: Query<(Entity, &HitPoints, &Position, &Name)>,
+    q_combatants: Query<(Entity, &HitPoints, &Position, &Name, Has<Player>, Option<&XpReward>, Option<&LootTable>)>,
     mut evt_killed: EventWriter<ActorKilledEvent>,
+    mut evt_loot: EventWriter<DropLootEvent>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
 ) {
-    for (entity, hp, pos, name) in q_combatants.iter() {
+    for (entity, hp, pos, name, is_player, xp, loot) in q_combatants.iter() {
         if hp.0 <= 0 {
             commands.entity(entity).despawn();
+
+            if let Some(table) = loot {
+                evt_loot.write(DropLootEvent {
+                    position: pos.0,
+                    table: table.clone(),
+                });
+            }
+
             let pos = IVec2::from(pos.0).as_uvec2();
 
             // Access the grid inside PathMap2d
@@ -144,10 +290,16 @@ fn death_system(
 
             evt_killed.write(ActorKilledEvent {
                 name: name.to_string(),
+                player: is_player,
+                xp: xp.map_or(0, |xp| xp.0),
             });
 
             // TODO: Move to UI
             log.push(format!("{} was killed!", name.as_str()));
+
+            if is_player {
+                next_phase.set(GamePhase::GameOver);
+            }
         }
     }
 }
@@ -157,4 +309,20 @@ fn get_pathmap_grid_mut(map: &mut PathMap2d) -> &mut Grid<bool> {
         // SAFELY cast PathMap2d to a Grid<bool> because PathMap2d wraps Grid<bool>
         &mut *(map as *mut PathMap2d as *mut Grid<bool>)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AttackOutcome, HIT_TARGET};
+
+    #[test]
+    fn natural_rolls_override_the_odds() {
+        // Hopeless and certain attacks still fumble and crit
+        assert_eq!(AttackOutcome::Fumble, AttackOutcome::from_roll(1, 100, 0));
+        assert_eq!(AttackOutcome::Critical, AttackOutcome::from_roll(20, 0, 100));
+
+        // Half of strength counts towards hitting
+        assert_eq!(AttackOutcome::Miss, AttackOutcome::from_roll(HIT_TARGET + 1, 3, 3));
+        assert_eq!(AttackOutcome::Hit, AttackOutcome::from_roll(HIT_TARGET + 2, 3, 3));
+    }
+}
@@ -1,46 +1,139 @@
+use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+use bevy::prelude::*;
 use serde::Deserialize;
 use std::ops::Range;
+use thiserror::Error;
 
+/// Path of the map generation settings, relative to the `assets` folder.
+pub const MAP_SETTINGS_FILE_NAME: &str = "map_settings.ron";
 
-#[derive(Debug, Deserialize)]
+/// The strategy [MapGenerator](crate::map::MapGenerator) uses to lay out a floor.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapAlgorithm {
+    /// Random non-overlapping rooms, each linked to the previous one.
+    #[default]
+    Rooms,
+    /// Binary space partition, fills the whole map with connected rooms.
+    Bsp,
+    /// Cellular automata caverns, trimmed down to the largest connected cave.
+    Cave,
+}
+
+#[derive(Asset, TypePath, Debug, Deserialize, Clone)]
 pub struct MapGenSettings {
-    pub seed: u64,
+    #[serde(default)]
+    pub algorithm: MapAlgorithm,
+    /// Pins every floor to this seed instead of the run's seed, handy while
+    /// tuning the generator.
+    #[serde(default)]
+    pub seed: Option<u64>,
     pub iterations: u32,
     pub map_size: [u32;2],
     pub room_size: Range<u32>,
     pub monsters_per_room: Range<u32>,
-    //pub items_per_room: Range<u32>,
+    /// Pools of water or lava and piles of rubble per room.
+    #[serde(default = "default_features_per_room")]
+    pub features_per_room: Range<u32>,
+    /// Items lying around per room, vault item spawns come on top.
+    #[serde(default = "default_items_per_room")]
+    pub items_per_room: Range<u32>,
+}
+
+fn default_features_per_room() -> Range<u32> {
+    0..2
+}
+
+fn default_items_per_room() -> Range<u32> {
+    0..2
 }
 
 impl Default for MapGenSettings {
     fn default() -> Self {
         Self {
-            seed: 5,
+            algorithm: MapAlgorithm::default(),
+            seed: None,
             iterations: 15,
             map_size: [80, 40],
             room_size: 3..15,
             monsters_per_room: 0..4,
-            //items_per_room: 0..2,
+            features_per_room: default_features_per_room(),
+            items_per_room: default_items_per_room(),
         }
     }
 }
 
-// pub fn try_get_map_settings() -> Result<MapGenSettings, String> {
-//     let result = read_to_string(format!(
-//         "{}/assets/{}",
-//         env!("CARGO_MANIFEST_DIR"),
-//         MAP_SETTINGS_FILE_NAME
-//     ));
+/// Handle to the [MapGenSettings] loaded from [MAP_SETTINGS_FILE_NAME].
+#[derive(Resource)]
+pub struct MapGenSettingsHandle(pub Handle<MapGenSettings>);
+
+#[derive(Debug, Error)]
+pub enum MapGenSettingsLoaderError {
+    #[error("Error reading {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Error parsing {path}: {source}")]
+    Parse {
+        path: String,
+        #[source]
+        source: ron::error::SpannedError,
+    },
+}
+
+/// Loads [MapGenSettings] from RON files.
+#[derive(Default)]
+pub struct MapGenSettingsLoader;
+
+impl AssetLoader for MapGenSettingsLoader {
+    type Asset = MapGenSettings;
+    type Settings = ();
+    type Error = MapGenSettingsLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let path = load_context.path().display().to_string();
+
+        let mut bytes = Vec::new();
+        if let Err(source) = reader.read_to_end(&mut bytes).await {
+            return Err(MapGenSettingsLoaderError::Io { path, source });
+        }
+
+        ron::de::from_bytes::<MapGenSettings>(&bytes)
+            .map_err(|source| MapGenSettingsLoaderError::Parse { path, source })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
 
-//     let file_string = match result {
-//         Ok(file_string) => file_string,
-//         Err(_) => return Err(format!("Error reading {}", MAP_SETTINGS_FILE_NAME)),
-//     };
+pub fn load_map_settings(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load::<MapGenSettings>(MAP_SETTINGS_FILE_NAME);
+    commands.insert_resource(MapGenSettingsHandle(handle));
+}
 
-//     let settings: MapGenSettings = match from_str(file_string.as_str()) {
-//         Ok(settings) => settings,
-//         Err(e) => return Err(format!("Error parsing {}: {}", MAP_SETTINGS_FILE_NAME, e)),
-//     };
+pub fn log_map_settings_errors(mut evt_failed: EventReader<AssetLoadFailedEvent<MapGenSettings>>) {
+    for ev in evt_failed.read() {
+        error!("{} could not be loaded, using default map settings: {}", ev.path, ev.error);
+    }
+}
 
-//     Ok(settings)
-// }
+/// Returns the loaded [MapGenSettings], or the defaults if the asset isn't available (yet).
+pub fn current_map_settings(
+    handle: Option<&MapGenSettingsHandle>,
+    assets: &Assets<MapGenSettings>,
+) -> MapGenSettings {
+    match handle.and_then(|h| assets.get(&h.0)) {
+        Some(settings) => settings.clone(),
+        None => {
+            warn!("{} is not loaded, using default map settings", MAP_SETTINGS_FILE_NAME);
+            MapGenSettings::default()
+        }
+    }
+}
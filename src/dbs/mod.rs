@@ -2,4 +2,5 @@ pub mod psqldb;
 pub mod playerdb;
 pub mod weapondb;
 pub mod redisdb;
-pub mod mongodb;
\ No newline at end of file
+pub mod mongodb;
+pub mod rundb;
\ No newline at end of file
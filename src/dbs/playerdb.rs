@@ -16,6 +16,7 @@ pub struct PlayerDb {
     pub max_hp: i32,
     pub defense: i32,
     pub strength: i32,
+    pub level: i32,
     pub inventory_id: Option<Uuid>,
 }
 #[derive(Debug)]
@@ -25,6 +26,7 @@ pub struct PlayerDbNoID {
     pub max_hp: i32,
     pub defense: i32,
     pub strength: i32,
+    pub level: i32,
 }
 
 #[derive(Debug)]
@@ -59,7 +61,7 @@ impl PlayerDb {
         let row = client.query_one(
             "INSERT INTO player (name, hp, max_hp, defense, strength)
              VALUES ($1, $2, $3, $4, $5)
-             RETURNING id, name, hp, max_hp, defense, strength, inventory_id",
+             RETURNING id, name, hp, max_hp, defense, strength, level, inventory_id",
             &[&name, &hp, &max_hp, &defense, &strength],
         ).await?;
 
@@ -70,7 +72,8 @@ impl PlayerDb {
             max_hp: row.get(3),
             defense: row.get(4),
             strength: row.get(5),
-            inventory_id: row.get(6),
+            level: row.get(6),
+            inventory_id: row.get(7),
         };
 
         // players defaut score on creation: 0 kills
@@ -79,6 +82,32 @@ impl PlayerDb {
         Ok(player)
     }
 
+    /// Saves the stats picked when levelling up.
+    pub async fn update_level(
+        psql: Arc<Mutex<Client>>,
+        name: &str,
+        level: i32,
+        max_hp: i32,
+        defense: i32,
+        strength: i32,
+    ) -> Result<u64, Error> {
+        let client = psql.lock().await;
+        client.execute(
+            "UPDATE player SET level = $2, max_hp = $3, defense = $4, strength = $5 WHERE name = $1",
+            &[&name, &level, &max_hp, &defense, &strength],
+        ).await
+    }
+
+    /// Level, max HP, defense and strength saved by [PlayerDb::update_level].
+    pub async fn get_level(psql: Arc<Mutex<Client>>, name: &str) -> Result<(i32, i32, i32, i32), Error> {
+        let client = psql.lock().await;
+        let row = client.query_one(
+            "SELECT level, max_hp, defense, strength FROM player WHERE name = $1",
+            &[&name],
+        ).await?;
+        Ok((row.get(0), row.get(1), row.get(2), row.get(3)))
+    }
+
     pub async fn get_player_full_data(
         psql: Arc<Mutex<Client>>,
         player_name: &str,
@@ -106,24 +135,25 @@ impl PlayerDb {
                     max_hp: row.get(2),
                     defense: row.get(3),
                     strength: row.get(4),
+                    level: row.get(5),
                 });
             }
 
             if inventory_info.is_none() {
                 inventory_info = Some(InventoryDbNoID {
-                    gold: row.get(5),
+                    gold: row.get(6),
                 });
             }
 
             // Weapon data per row
             weapons.push(WeaponDBNoID {
-                name: row.get(6),
-                damage: row.get(7),
-                weight: row.get(8),
-                upgrade: row.get(9),
-                perk: row.get(10),
-                weapon_type: row.get(11),
-                predicted_price: Some(row.get(12)),
+                name: row.get(7),
+                damage: row.get(8),
+                weight: row.get(9),
+                upgrade: row.get(10),
+                perk: row.get(11),
+                weapon_type: row.get(12),
+                predicted_price: Some(row.get(13)),
             });
         }
 
@@ -134,3 +164,64 @@ impl PlayerDb {
         })
     }
 }
+
+impl InventoryDb {
+    /// Replaces the items in a player's inventory with `items`, given as item name and count.
+    pub async fn save_items(
+        psql: Arc<Mutex<Client>>,
+        player_name: &str,
+        items: &[(String, i32)],
+    ) -> Result<(), Error> {
+        let mut client = psql.lock().await;
+        let transaction = client.transaction().await?;
+
+        transaction.execute(
+            "DELETE FROM inventory_item
+             WHERE inventory_id = (SELECT inventory_id FROM player WHERE name = $1)",
+            &[&player_name],
+        ).await?;
+
+        for (item, count) in items {
+            transaction.execute(
+                "INSERT INTO inventory_item (inventory_id, item, count)
+                 SELECT inventory_id, $2, $3 FROM player WHERE name = $1",
+                &[&player_name, item, count],
+            ).await?;
+        }
+
+        transaction.commit().await
+    }
+
+    /// The items in a player's inventory as item name and count.
+    pub async fn get_items(psql: Arc<Mutex<Client>>, player_name: &str) -> Result<Vec<(String, i32)>, Error> {
+        let client = psql.lock().await;
+        let rows = client.query(
+            "SELECT ii.item, ii.count FROM inventory_item ii
+             JOIN player p ON p.inventory_id = ii.inventory_id
+             WHERE p.name = $1",
+            &[&player_name],
+        ).await?;
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+    pub async fn get_gold(psql: Arc<Mutex<Client>>, player_name: &str) -> Result<i32, Error> {
+        let client = psql.lock().await;
+        let row = client.query_one(
+            "SELECT i.gold FROM inventory i JOIN player p ON p.inventory_id = i.id WHERE p.name = $1",
+            &[&player_name],
+        ).await?;
+        Ok(row.get(0))
+    }
+
+    /// Adds gold found in the dungeon, returning the new total.
+    pub async fn add_gold(psql: Arc<Mutex<Client>>, player_name: &str, amount: i32) -> Result<i32, Error> {
+        let client = psql.lock().await;
+        let row = client.query_one(
+            "UPDATE inventory SET gold = gold + $2
+             WHERE id = (SELECT inventory_id FROM player WHERE name = $1)
+             RETURNING gold",
+            &[&player_name, &amount],
+        ).await?;
+        Ok(row.get(0))
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_postgres::{Client, Error};
+use uuid::Uuid;
+
+/// A finished run, see `run_summary`.
+#[derive(Debug)]
+pub struct RunSummaryDb {
+    pub player_name: String,
+    /// `died` or `won`.
+    pub outcome: String,
+    pub turns: i32,
+    pub floor: i32,
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+    pub weapon: String,
+    pub seed: String,
+    pub practice: bool,
+    /// Monster name and how many were killed.
+    pub kills: Vec<(String, i32)>,
+}
+
+impl RunSummaryDb {
+    pub async fn create(psql: Arc<Mutex<Client>>, run: &RunSummaryDb) -> Result<Uuid, Error> {
+        let client = psql.lock().await;
+        let row = client.query_one(
+            "INSERT INTO run_summary (player_name, outcome, turns, floor, damage_dealt, damage_taken, weapon, seed, practice)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             RETURNING id",
+            &[&run.player_name, &run.outcome, &run.turns, &run.floor, &run.damage_dealt, &run.damage_taken, &run.weapon, &run.seed, &run.practice],
+        ).await?;
+        let id: Uuid = row.get(0);
+
+        for (monster, count) in &run.kills {
+            client.execute(
+                "INSERT INTO run_kill (run_id, monster, count) VALUES ($1, $2, $3)",
+                &[&id, monster, count],
+            ).await?;
+        }
+
+        Ok(id)
+    }
+}
@@ -51,4 +51,48 @@ impl WeaponDB {
             predicted_price: row.get(7),
         })
     }
-}
\ No newline at end of file
+
+    /// Puts a weapon in the named player's `weapon_inventory`.
+    pub async fn add_to_inventory(
+        psql: Arc<Mutex<Client>>,
+        weapon_id: Uuid,
+        player_name: &str,
+    ) -> Result<u64, Error> {
+        let client = psql.lock().await;
+        client.execute(
+            "INSERT INTO weapon_inventory (inventory_id, weapon_id)
+             SELECT inventory_id, $1 FROM player WHERE name = $2",
+            &[&weapon_id, &player_name],
+        ).await
+    }
+
+    /// Every weapon in the named player's `weapon_inventory`.
+    pub async fn get_for_player(
+        psql: Arc<Mutex<Client>>,
+        player_name: &str,
+    ) -> Result<Vec<WeaponDBNoID>, Error> {
+        let client = psql.lock().await;
+        let rows = client.query(
+            "SELECT w.name, w.damage, w.weight, w.upgrade, w.perk, w.weapon_type, w.predicted_price
+             FROM weapon w
+             JOIN weapon_inventory wi ON wi.weapon_id = w.id
+             JOIN player p ON p.inventory_id = wi.inventory_id
+             WHERE p.name = $1
+             ORDER BY w.name",
+            &[&player_name],
+        ).await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| WeaponDBNoID {
+                name: row.get(0),
+                damage: row.get(1),
+                weight: row.get(2),
+                upgrade: row.get(3),
+                perk: row.get(4),
+                weapon_type: row.get(5),
+                predicted_price: row.get(6),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,227 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use sark_grids::{Grid, SizedGrid};
+
+use crate::{
+    bestiary::Bestiary,
+    combat::HitPoints,
+    item::{Item, ItemBundle, ItemKind},
+    map::{self, Map, MapGenAssets, MapGenSetupSet, MapTile},
+    monster::{Monster, MonsterBundle},
+    movement::Position,
+    player::Player,
+    status::StatusEffects,
+    turn_system::Energy,
+    ui::PrintLog,
+    visibility::{MapMemory, MapView},
+    AppState, GamePhase,
+};
+
+/// Taking the down stairs on the last floor wins the run.
+pub const FLOOR_COUNT: u32 = 10;
+
+pub struct DungeonPlugin;
+
+impl Plugin for DungeonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DungeonLevel>()
+            .init_resource::<VisitedFloors>()
+            .add_event::<ChangeFloorEvent>()
+            .add_systems(OnEnter(AppState::Lore), reset_dungeon.before(MapGenSetupSet))
+            .add_systems(Update, change_floor_system.run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// The floor the player is currently on, `0` being the entrance.
+#[derive(Resource, Default, Debug)]
+pub struct DungeonLevel {
+    pub depth: u32,
+}
+
+/// Floors the player has left, keyed by depth.
+#[derive(Resource, Default)]
+pub struct VisitedFloors(pub HashMap<u32, StoredFloor>);
+
+/// Everything needed to put a floor back the way the player left it.
+#[derive(Clone)]
+pub struct StoredFloor {
+    pub map: Grid<MapTile>,
+    pub memory: Vec<bool>,
+    pub monsters: Vec<StoredMonster>,
+    pub items: Vec<(IVec2, ItemKind)>,
+}
+
+#[derive(Clone)]
+pub struct StoredMonster {
+    pub name: String,
+    pub position: IVec2,
+    pub hp: i32,
+    pub energy: i32,
+    pub status: StatusEffects,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FloorChange {
+    Down,
+    Up,
+}
+
+impl FloorChange {
+    /// The stairs the player has to stand on to take them.
+    pub fn stairs(&self) -> MapTile {
+        match self {
+            FloorChange::Down => MapTile::StairsDown,
+            FloorChange::Up => MapTile::StairsUp,
+        }
+    }
+
+    /// The stairs the player arrives on at the other end.
+    fn arrival(&self) -> MapTile {
+        match self {
+            FloorChange::Down => MapTile::StairsUp,
+            FloorChange::Up => MapTile::StairsDown,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct ChangeFloorEvent(pub FloorChange);
+
+fn reset_dungeon(mut level: ResMut<DungeonLevel>, mut visited: ResMut<VisitedFloors>) {
+    level.depth = 0;
+    visited.0.clear();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn change_floor_system(
+    mut commands: Commands,
+    mut evt_change: EventReader<ChangeFloorEvent>,
+    mut level: ResMut<DungeonLevel>,
+    mut visited: ResMut<VisitedFloors>,
+    mut log: ResMut<PrintLog>,
+    assets: MapGenAssets,
+    q_map: Query<(Entity, &Map)>,
+    q_monsters: Query<(Entity, &Name, &Position, &HitPoints, &Energy, &StatusEffects), With<Monster>>,
+    q_items: Query<(Entity, &Item, &Position)>,
+    q_player: Query<(Entity, &MapMemory), With<Player>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+) {
+    let Some(ChangeFloorEvent(change)) = evt_change.read().last() else {
+        return;
+    };
+    let (Ok((map_entity, map)), Ok((player, memory))) = (q_map.single(), q_player.single()) else {
+        return;
+    };
+
+    if *change == FloorChange::Down && level.depth + 1 == FLOOR_COUNT {
+        log.push("You step out of the bottom of the sector. The run is won!".to_string());
+        next_phase.set(GamePhase::Victory);
+        return;
+    }
+
+    let target = match change {
+        FloorChange::Down => level.depth + 1,
+        FloorChange::Up => match level.depth.checked_sub(1) {
+            Some(depth) => depth,
+            None => return,
+        },
+    };
+
+    // Stash the floor we're leaving
+    let monsters = q_monsters
+        .iter()
+        .map(|(entity, name, pos, hp, energy, status)| {
+            commands.entity(entity).despawn();
+            StoredMonster {
+                name: name.to_string(),
+                position: pos.0,
+                hp: hp.0,
+                energy: energy.0,
+                status: status.clone(),
+            }
+        })
+        .collect();
+
+    let items = q_items
+        .iter()
+        .map(|(entity, item, pos)| {
+            commands.entity(entity).despawn();
+            (pos.0, item.0)
+        })
+        .collect();
+
+    visited.0.insert(level.depth, StoredFloor {
+        map: map.0.clone(),
+        memory: memory.0.clone(),
+        monsters,
+        items,
+    });
+    commands.entity(map_entity).despawn();
+
+    level.depth = target;
+
+    match visited.0.remove(&target) {
+        Some(floor) => restore_floor(&mut commands, floor, player, change.arrival(), target, &assets.bestiary()),
+        None => map::generate_floor(&mut commands, &assets, Some(player), target),
+    }
+
+    match change {
+        FloorChange::Down => log.push(format!("You descend to floor {}.", target + 1)),
+        FloorChange::Up => log.push(format!("You climb back up to floor {}.", target + 1)),
+    }
+}
+
+fn restore_floor(
+    commands: &mut Commands,
+    floor: StoredFloor,
+    player: Entity,
+    arrival: MapTile,
+    depth: u32,
+    bestiary: &Bestiary,
+) {
+    let start = floor
+        .map
+        .iter()
+        .position(|tile| *tile == arrival)
+        .map(|i| floor.map.transform_itl(i))
+        .unwrap_or_else(|| {
+            warn!("Floor {} has no {:?}, arriving at the origin", depth, arrival);
+            IVec2::ZERO
+        });
+
+    for stored in floor.monsters.iter() {
+        spawn_stored_monster(commands, stored, bestiary, depth);
+    }
+    for (p, kind) in floor.items.iter() {
+        commands.spawn(ItemBundle::new(*kind, *p));
+    }
+
+    commands.entity(player)
+        .insert(Position(start))
+        .insert(MapView(Grid::new(floor.map.size())))
+        .insert(MapMemory(floor.memory));
+
+    commands.spawn(Map(floor.map));
+}
+
+/// Spawns a monster the way it was stored, returning `None` for unknown monsters.
+pub fn spawn_stored_monster(
+    commands: &mut Commands,
+    stored: &StoredMonster,
+    bestiary: &Bestiary,
+    depth: u32,
+) -> Option<Entity> {
+    let Some(template) = bestiary.get(&stored.name) else {
+        warn!("Unknown monster {} on floor {}", stored.name, depth);
+        return None;
+    };
+    let mut monster = MonsterBundle::from_template(template);
+    monster.scale_to_depth(depth);
+    monster.movable.position = Position(stored.position);
+    monster.movable.energy = Energy(stored.energy);
+    monster.combatant_bundle.hp = HitPoints(stored.hp);
+    monster.combatant_bundle.status = stored.status.clone();
+
+    Some(commands.spawn(monster).id())
+}
@@ -0,0 +1,437 @@
+//! The player's weapon, armor and trinket slots.
+//!
+//! The player's [BaseStats] only change when levelling up. Whenever they or
+//! the [Equipment] change, `Defense`, `Strength`, [AttackDice], [Speed] and
+//! the weapon's [OnHitStatus] are worked out again from scratch.
+//!
+//! The generated [Weapon] is wielded when entering [AppState::InGame], the
+//! other weapons in the player's `weapon_inventory` can be swapped to from
+//! the inventory overlay.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{prelude::*, tasks::IoTaskPool};
+use bracket_random::prelude::DiceType;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::ResolveActionsSet,
+    combat::{AttackDice, Defense, Strength},
+    dbs::{psqldb::Database, weapondb::WeaponDB},
+    generating_weapon::GeneratedWeapon,
+    item::{Inventory, ItemKind},
+    main_menu::CharacterName,
+    player::{Player, PLAYER_SPEED},
+    replay::{playing_back, ReplayRecorder},
+    status::{perk_status, OnHitStatus, StatusKind, PERK_CHANCE},
+    turn_system::{turns_running, Speed},
+    ui::PrintLog,
+    weapon_prediction::bridge::Weapon,
+    AppState,
+};
+
+/// Weight a weapon can have before it slows its wielder down.
+const FREE_WEIGHT: f64 = 5.0;
+
+/// Extra weight that costs a point of speed.
+const WEIGHT_PER_SPEED: f64 = 2.0;
+
+/// No equipment slows the player down further than this.
+const MIN_SPEED: i32 = 5;
+
+/// Most dice a weapon rolls, higher damage makes the dice bigger instead.
+const MAX_DICE: i32 = 6;
+
+pub struct EquipmentPlugin;
+
+impl Plugin for EquipmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ChangeEquipmentEvent>()
+            .init_resource::<StoredWeaponsPending>()
+            .add_systems(OnEnter(AppState::Lore), unequip_all)
+            .add_systems(
+                OnEnter(AppState::InGame),
+                (equip_generated_weapon, load_stored_weapons.run_if(not(playing_back))),
+            )
+            .add_systems(
+                Update,
+                (
+                    change_equipment_system.after(ResolveActionsSet).run_if(turns_running),
+                    receive_stored_weapons,
+                    // Also catches level ups, once turns are running again
+                    recalculate_stats_system
+                        .after(change_equipment_system)
+                        .after(receive_stored_weapons)
+                        .run_if(turns_running),
+                )
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Slot {
+    Weapon,
+    Armor,
+    Trinket,
+}
+
+/// The player's stats with nothing equipped.
+#[derive(Component, Debug, Clone)]
+pub struct BaseStats {
+    pub defense: i32,
+    pub strength: i32,
+    /// Rolled when fighting bare handed.
+    pub attack_dice: DiceType,
+    pub speed: i32,
+}
+
+impl Default for BaseStats {
+    fn default() -> Self {
+        Self {
+            defense: 1,
+            strength: 3,
+            attack_dice: DiceType::new(5, 3, 0),
+            speed: PLAYER_SPEED,
+        }
+    }
+}
+
+/// A weapon the player carries.
+#[derive(Debug, Clone)]
+pub struct EquippedWeapon {
+    pub name: String,
+    pub weapon_type: String,
+    pub dice: DiceType,
+    /// Speed lost to the weapon's weight.
+    pub speed_penalty: i32,
+    pub perk: Option<StatusKind>,
+}
+
+impl EquippedWeapon {
+    pub fn from_weapon(weapon: &Weapon) -> Self {
+        Self {
+            name: weapon.name.clone(),
+            weapon_type: weapon.weapon_type.clone(),
+            dice: weapon_dice(weapon.damage),
+            speed_penalty: speed_penalty(weapon.weight),
+            perk: perk_status(&weapon.perk),
+        }
+    }
+}
+
+/// What the player has on.
+#[derive(Component, Default, Debug, Clone)]
+pub struct Equipment {
+    /// Every weapon carried, only one of them is wielded.
+    pub weapons: Vec<EquippedWeapon>,
+    pub wielded: Option<usize>,
+    pub armor: Option<ItemKind>,
+    pub trinket: Option<ItemKind>,
+}
+
+impl Equipment {
+    pub fn weapon(&self) -> Option<&EquippedWeapon> {
+        self.wielded.and_then(|i| self.weapons.get(i))
+    }
+
+    pub fn is_empty(&self, slot: Slot) -> bool {
+        match slot {
+            Slot::Weapon => self.wielded.is_none(),
+            Slot::Armor => self.armor.is_none(),
+            Slot::Trinket => self.trinket.is_none(),
+        }
+    }
+
+    /// The weapon after the wielded one, wrapping around.
+    pub fn next_weapon(&self) -> Option<usize> {
+        if self.weapons.len() < 2 {
+            return None;
+        }
+        Some(self.wielded.map_or(0, |i| (i + 1) % self.weapons.len()))
+    }
+}
+
+/// How a worn item changes its wearer's stats.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StatBonus {
+    pub defense: i32,
+    pub strength: i32,
+    pub speed: i32,
+}
+
+pub fn item_bonus(kind: ItemKind) -> StatBonus {
+    match kind {
+        ItemKind::LeatherArmor => StatBonus { defense: 1, ..default() },
+        ItemKind::ChainMail => StatBonus { defense: 3, speed: -3, ..default() },
+        ItemKind::PowerCharm => StatBonus { strength: 2, ..default() },
+        ItemKind::SwiftCharm => StatBonus { speed: 3, ..default() },
+        _ => StatBonus::default(),
+    }
+}
+
+/// The stats that come out of `base` with `equipment` on.
+pub fn derived_stats(base: &BaseStats, equipment: &Equipment) -> (Defense, Strength, AttackDice, Speed) {
+    let bonus = [equipment.armor, equipment.trinket]
+        .into_iter()
+        .flatten()
+        .map(item_bonus)
+        .fold(StatBonus::default(), |a, b| StatBonus {
+            defense: a.defense + b.defense,
+            strength: a.strength + b.strength,
+            speed: a.speed + b.speed,
+        });
+
+    let weapon = equipment.weapon();
+    let dice = weapon.map_or(base.attack_dice, |w| w.dice);
+    let penalty = weapon.map_or(0, |w| w.speed_penalty);
+
+    (
+        Defense(base.defense + bonus.defense),
+        Strength(base.strength + bonus.strength),
+        AttackDice(dice),
+        Speed((base.speed + bonus.speed - penalty).max(MIN_SPEED)),
+    )
+}
+
+/// Dice averaging about two thirds of a generated damage value, which puts a
+/// typical 15 damage weapon on par with the player's bare 5d3.
+pub fn weapon_dice(damage: i32) -> DiceType {
+    let damage = damage.max(1);
+    let count = (damage / 5).clamp(1, MAX_DICE);
+    let average = damage * 2 / 3;
+    let faces = (2 * average / count - 1).max(2);
+    DiceType::new(count, faces, 0)
+}
+
+pub fn speed_penalty(weight: f64) -> i32 {
+    ((weight - FREE_WEIGHT).max(0.0) / WEIGHT_PER_SPEED).round() as i32
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EquipmentChange {
+    /// Put on a carried armor or trinket, whatever was in its slot goes back in the pack.
+    Wear(ItemKind),
+    /// Put the armor or trinket back in the pack.
+    TakeOff(Slot),
+    /// Switch to another carried weapon, by its index in [Equipment::weapons].
+    Wield(usize),
+}
+
+/// An actor changes what it has on, checked by the action resolver.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ChangeEquipmentEvent {
+    pub actor: Entity,
+    pub change: EquipmentChange,
+}
+
+/// Weapons fetched from `weapon_inventory`, waiting to be handed to the player.
+#[derive(Resource, Default)]
+struct StoredWeaponsPending(Arc<Mutex<Option<Vec<Weapon>>>>);
+
+fn unequip_all(mut q_equipment: Query<&mut Equipment, With<Player>>) {
+    for mut equipment in q_equipment.iter_mut() {
+        *equipment = Equipment::default();
+    }
+}
+
+pub fn equip_generated_weapon(
+    weapon: Option<Res<GeneratedWeapon>>,
+    mut q_player: Query<&mut Equipment, With<Player>>,
+    mut log: ResMut<PrintLog>,
+) {
+    let (Some(weapon), Ok(mut equipment)) = (weapon, q_player.single_mut()) else {
+        return;
+    };
+
+    let equipped = EquippedWeapon::from_weapon(&weapon.0);
+    log.push(format!(
+        "You ready the {} ({}d{}).",
+        equipped.name, equipped.dice.n_dice, equipped.dice.die_type
+    ));
+    equipment.weapons.push(equipped);
+    equipment.wielded = Some(equipment.weapons.len() - 1);
+}
+
+fn load_stored_weapons(
+    name: Res<CharacterName>,
+    psql: Res<Database>,
+    pending: Res<StoredWeaponsPending>,
+) {
+    let db_client = psql.client.clone();
+    let name = name.0.clone();
+    let pending = Arc::clone(&pending.0);
+
+    IoTaskPool::get().spawn(async move {
+        match WeaponDB::get_for_player(db_client, &name).await {
+            Ok(weapons) => {
+                let weapons = weapons
+                    .into_iter()
+                    .map(|w| Weapon {
+                        name: w.name,
+                        damage: w.damage,
+                        weight: w.weight,
+                        upgrade: w.upgrade,
+                        perk: w.perk,
+                        weapon_type: w.weapon_type,
+                        predicted_price: w.predicted_price,
+                    })
+                    .collect();
+                *pending.lock().unwrap() = Some(weapons);
+            }
+            Err(e) => eprintln!(">>X<< Failed to load stored weapons: {}", e),
+        }
+    }).detach();
+}
+
+fn receive_stored_weapons(
+    pending: Res<StoredWeaponsPending>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut q_player: Query<&mut Equipment, With<Player>>,
+) {
+    let Some(weapons) = pending.0.lock().unwrap().take() else {
+        return;
+    };
+    if let Ok(mut equipment) = q_player.single_mut() {
+        carry_stored_weapons(&mut equipment, &weapons);
+    }
+    // A replay starts with the same weapons, the character may have more by then
+    recorder.0.stored_weapons = weapons;
+}
+
+/// Adds the weapons kept from earlier runs to the ones carried.
+pub fn carry_stored_weapons(equipment: &mut Equipment, weapons: &[Weapon]) {
+    for weapon in weapons.iter() {
+        // The generated weapon may already have been saved
+        if equipment.weapons.iter().any(|w| w.name == weapon.name) {
+            continue;
+        }
+        equipment.weapons.push(EquippedWeapon::from_weapon(weapon));
+    }
+}
+
+fn change_equipment_system(
+    mut evt_change: EventReader<ChangeEquipmentEvent>,
+    mut q_actors: Query<(&mut Equipment, &mut Inventory)>,
+    q_player: Query<(), With<Player>>,
+    mut log: ResMut<PrintLog>,
+) {
+    for ev in evt_change.read() {
+        let Ok((mut equipment, mut inventory)) = q_actors.get_mut(ev.actor) else {
+            continue;
+        };
+
+        let message = match ev.change {
+            EquipmentChange::Wear(kind) => {
+                let Some(index) = inventory.0.iter().position(|k| *k == kind) else {
+                    continue;
+                };
+                let slot = match kind.slot() {
+                    Some(Slot::Armor) => &mut equipment.armor,
+                    Some(Slot::Trinket) => &mut equipment.trinket,
+                    _ => continue,
+                };
+                inventory.0.remove(index);
+                if let Some(previous) = slot.replace(kind) {
+                    inventory.0.push(previous);
+                }
+                format!("You {}.", kind.used())
+            }
+            EquipmentChange::TakeOff(slot) => {
+                let worn = match slot {
+                    Slot::Armor => equipment.armor.take(),
+                    Slot::Trinket => equipment.trinket.take(),
+                    Slot::Weapon => None,
+                };
+                let Some(kind) = worn else {
+                    continue;
+                };
+                inventory.0.push(kind);
+                format!("You take off the {}.", kind.name())
+            }
+            EquipmentChange::Wield(index) => {
+                let Some(weapon) = equipment.weapons.get(index) else {
+                    continue;
+                };
+                let message = format!(
+                    "You switch to the {} ({}d{}).",
+                    weapon.name, weapon.dice.n_dice, weapon.dice.die_type
+                );
+                equipment.wielded = Some(index);
+                message
+            }
+        };
+
+        if q_player.contains(ev.actor) {
+            log.push(message);
+        }
+    }
+}
+
+fn recalculate_stats_system(
+    mut commands: Commands,
+    mut q_actors: Query<
+        (Entity, &BaseStats, &Equipment, &mut Defense, &mut Strength, &mut AttackDice, &mut Speed),
+        Or<(Changed<BaseStats>, Changed<Equipment>)>,
+    >,
+) {
+    for (entity, base, equipment, mut defense, mut strength, mut dice, mut speed) in q_actors.iter_mut() {
+        (*defense, *strength, *dice, *speed) = derived_stats(base, equipment);
+
+        match equipment.weapon().and_then(|w| w.perk) {
+            Some(kind) => {
+                commands.entity(entity).insert(OnHitStatus {
+                    effect: kind.effect(),
+                    chance: PERK_CHANCE,
+                });
+            }
+            None => {
+                commands.entity(entity).remove::<OnHitStatus>();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bracket_random::prelude::DiceType;
+
+    use super::{derived_stats, speed_penalty, weapon_dice, BaseStats, Equipment, EquippedWeapon};
+    use crate::item::ItemKind;
+
+    #[test]
+    fn weapon_stats_become_dice_and_speed() {
+        let dice = weapon_dice(15);
+        assert_eq!((3, 5), (dice.n_dice, dice.die_type));
+
+        // Huge damage values get bigger dice, not more of them
+        assert_eq!(6, weapon_dice(200).n_dice);
+        assert_eq!((1, 2), (weapon_dice(0).n_dice, weapon_dice(0).die_type));
+
+        assert_eq!(0, speed_penalty(4.0));
+        assert_eq!(3, speed_penalty(10.0));
+    }
+
+    #[test]
+    fn equipment_adds_up_on_top_of_base_stats() {
+        let base = BaseStats::default();
+        let mut equipment = Equipment::default();
+
+        let (defense, strength, dice, speed) = derived_stats(&base, &equipment);
+        assert_eq!((1, 3, 5, 25), (defense.0, strength.0, dice.0.n_dice, speed.0));
+
+        equipment.weapons.push(EquippedWeapon {
+            name: "Axe".to_string(),
+            weapon_type: "Axe".to_string(),
+            dice: DiceType::new(2, 8, 0),
+            speed_penalty: 4,
+            perk: None,
+        });
+        equipment.wielded = Some(0);
+        equipment.armor = Some(ItemKind::ChainMail);
+        equipment.trinket = Some(ItemKind::PowerCharm);
+
+        let (defense, strength, dice, speed) = derived_stats(&base, &equipment);
+        assert_eq!((4, 5, 8, 18), (defense.0, strength.0, dice.0.die_type, speed.0));
+    }
+}
@@ -0,0 +1,290 @@
+//! Experience from kills and levelling up.
+//!
+//! Every level gained lets the player raise one stat in the
+//! [GamePhase::LevelUp] prompt, turns are paused until it's picked. The level
+//! and stats are saved to the `player` table and every run starts from them.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{prelude::*, tasks::IoTaskPool};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::ResolveActionsSet,
+    combat::{ActorKilledEvent, DeathSystemSet, HitPoints, MaxHitPoints},
+    dbs::{playerdb::PlayerDb, psqldb::Database},
+    equipment::BaseStats,
+    main_menu::CharacterName,
+    player::Player,
+    practice::PracticeMode,
+    replay::{playing_back, Playback, ReplayRecorder},
+    turn_system::TakingATurn,
+    ui::PrintLog,
+    AppState, GamePhase,
+};
+
+/// Experience needed per level, level 1 to 2 takes this much, level 2 to 3 twice as much and so on.
+pub const XP_PER_LEVEL: i32 = 50;
+
+/// Max HP gained when picking health in the level up prompt.
+const LEVEL_UP_HP: i32 = 10;
+
+pub struct ExperiencePlugin;
+
+impl Plugin for ExperiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<LevelUpEvent>()
+            .init_resource::<LevelPending>()
+            .add_systems(OnEnter(AppState::InGame), load_level.run_if(not(playing_back)))
+            .add_systems(Update, receive_level.run_if(in_state(AppState::InGame)))
+            .add_systems(
+                PostUpdate,
+                award_xp_system
+                    .after(DeathSystemSet)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    level_up_input.run_if(not(playing_back)),
+                    // Played back picks are written before the resolver
+                    level_up_system.after(level_up_input).after(ResolveActionsSet),
+                )
+                    .run_if(in_state(GamePhase::LevelUp)),
+            );
+    }
+}
+
+/// Experience awarded for killing a monster.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct XpReward(pub i32);
+
+#[derive(Component, Debug, Clone)]
+pub struct Experience {
+    pub level: u32,
+    /// Experience towards the next level.
+    pub xp: i32,
+    /// Levels gained that haven't been spent on a stat yet.
+    pub unspent: u32,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Self {
+            level: 1,
+            xp: 0,
+            unspent: 0,
+        }
+    }
+}
+
+impl Experience {
+    /// Experience needed to reach the next level.
+    pub fn to_next_level(&self) -> i32 {
+        XP_PER_LEVEL * self.level as i32
+    }
+
+    /// Adds experience, returning how many levels were gained.
+    pub fn gain(&mut self, xp: i32) -> u32 {
+        self.xp += xp;
+
+        let mut gained = 0;
+        while self.xp >= self.to_next_level() {
+            self.xp -= self.to_next_level();
+            self.level += 1;
+            gained += 1;
+        }
+        self.unspent += gained;
+        gained
+    }
+}
+
+/// The stat raised in the level up prompt.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatChoice {
+    MaxHp,
+    Strength,
+    Defense,
+}
+
+/// The player spends a level on a stat, picked in the prompt or played back.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LevelUpEvent(pub StatChoice);
+
+/// The character's level and stats as saved in the `player` table.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SavedLevel {
+    pub level: u32,
+    pub max_hp: i32,
+    pub defense: i32,
+    pub strength: i32,
+}
+
+impl SavedLevel {
+    /// Puts the saved stats on the player, starting the run at full health.
+    pub fn apply(&self, experience: &mut Experience, hp: &mut HitPoints, max_hp: &mut MaxHitPoints, base: &mut BaseStats) {
+        experience.level = self.level;
+        max_hp.0 = self.max_hp;
+        hp.0 = self.max_hp;
+        base.defense = self.defense;
+        base.strength = self.strength;
+    }
+}
+
+/// The level read from the database, waiting to be put on the player.
+#[derive(Resource, Default)]
+struct LevelPending(Arc<Mutex<Option<SavedLevel>>>);
+
+fn load_level(name: Res<CharacterName>, psql: Res<Database>, pending: Res<LevelPending>) {
+    let db_client = psql.client.clone();
+    let name = name.0.clone();
+    let pending = Arc::clone(&pending.0);
+
+    IoTaskPool::get().spawn(async move {
+        match PlayerDb::get_level(db_client, &name).await {
+            Ok((level, max_hp, defense, strength)) => {
+                *pending.lock().unwrap() = Some(SavedLevel {
+                    level: level.max(1) as u32,
+                    max_hp,
+                    defense,
+                    strength,
+                });
+            }
+            Err(e) => eprintln!(">>X<< Failed to load level: {}", e),
+        }
+    }).detach();
+}
+
+fn receive_level(
+    pending: Res<LevelPending>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut q_player: Query<(&mut Experience, &mut HitPoints, &mut MaxHitPoints, &mut BaseStats), With<Player>>,
+) {
+    let Some(saved) = pending.0.lock().unwrap().take() else {
+        return;
+    };
+    if let Ok((mut experience, mut hp, mut max_hp, mut base)) = q_player.single_mut() {
+        saved.apply(&mut experience, &mut hp, &mut max_hp, &mut base);
+    }
+    // A replay starts from the same stats
+    recorder.0.level = Some(saved);
+}
+
+fn award_xp_system(
+    mut evt_killed: EventReader<ActorKilledEvent>,
+    mut q_player: Query<&mut Experience, With<Player>>,
+    phase: Res<State<GamePhase>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut log: ResMut<PrintLog>,
+) {
+    // Gone if the player died this frame
+    let Ok(mut experience) = q_player.single_mut() else {
+        evt_killed.clear();
+        return;
+    };
+
+    for ev in evt_killed.read().filter(|ev| !ev.player) {
+        if experience.gain(ev.xp) > 0 {
+            log.push(format!("You reach level {}!", experience.level));
+        }
+    }
+
+    let taking_turns = matches!(phase.get(), GamePhase::PlayerTurn | GamePhase::MonsterTurn);
+    if experience.unspent > 0 && taking_turns {
+        next_phase.set(GamePhase::LevelUp);
+    }
+}
+
+fn level_up_input(keyboard: Res<ButtonInput<KeyCode>>, mut evt_level_up: EventWriter<LevelUpEvent>) {
+    let choice = if keyboard.just_pressed(KeyCode::Digit1) {
+        StatChoice::MaxHp
+    } else if keyboard.just_pressed(KeyCode::Digit2) {
+        StatChoice::Strength
+    } else if keyboard.just_pressed(KeyCode::Digit3) {
+        StatChoice::Defense
+    } else {
+        return;
+    };
+    evt_level_up.write(LevelUpEvent(choice));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn level_up_system(
+    mut evt_level_up: EventReader<LevelUpEvent>,
+    mut q_player: Query<(&mut Experience, &mut HitPoints, &mut MaxHitPoints, &mut BaseStats), With<Player>>,
+    q_acting: Query<Has<Player>, With<TakingATurn>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut log: ResMut<PrintLog>,
+    name: Res<CharacterName>,
+    psql: Res<Database>,
+    playback: Option<Res<Playback>>,
+    practice: Res<PracticeMode>,
+) {
+    let Ok((mut experience, mut hp, mut max_hp, mut base)) = q_player.single_mut() else {
+        return;
+    };
+
+    for LevelUpEvent(choice) in evt_level_up.read() {
+        if experience.unspent == 0 {
+            break;
+        }
+
+        let picked = match choice {
+            StatChoice::MaxHp => {
+                max_hp.0 += LEVEL_UP_HP;
+                hp.0 += LEVEL_UP_HP;
+                format!("Max HP +{}", LEVEL_UP_HP)
+            }
+            StatChoice::Strength => {
+                base.strength += 1;
+                "Strength +1".to_string()
+            }
+            StatChoice::Defense => {
+                base.defense += 1;
+                "Defense +1".to_string()
+            }
+        };
+
+        experience.unspent -= 1;
+        log.push(format!("{}.", picked));
+
+        // Replays don't own the character's level, practice levels can be undone
+        if playback.is_some() || practice.0 {
+            continue;
+        }
+        let db_client = psql.client.clone();
+        let name = name.0.clone();
+        // Saved without equipment, which changes from run to run
+        let (level, max_hp, defense, strength) = (experience.level as i32, max_hp.0, base.defense, base.strength);
+        IoTaskPool::get().spawn(async move {
+            if let Err(e) = PlayerDb::update_level(db_client, &name, level, max_hp, defense, strength).await {
+                eprintln!(">>X<< Failed to save level: {}", e);
+            }
+        }).detach();
+    }
+
+    if experience.unspent == 0 {
+        // Hand the turn back to whoever had it
+        let monster_acting = q_acting.iter().any(|is_player| !is_player);
+        next_phase.set(if monster_acting { GamePhase::MonsterTurn } else { GamePhase::PlayerTurn });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Experience, XP_PER_LEVEL};
+
+    #[test]
+    fn gaining_xp_carries_over_between_levels() {
+        let mut experience = Experience::default();
+
+        assert_eq!(0, experience.gain(XP_PER_LEVEL - 1));
+        // One level and a bit into the next
+        assert_eq!(1, experience.gain(2));
+        assert_eq!((2, 1), (experience.level, experience.xp));
+
+        // Enough for two more levels at once
+        assert_eq!(2, experience.gain(XP_PER_LEVEL * 5));
+        assert_eq!((4, 1, 3), (experience.level, experience.xp, experience.unspent));
+    }
+}
@@ -9,7 +9,7 @@ use bevy::tasks::{AsyncComputeTaskPool, Task};
 use bevy_ascii_terminal::{color, StringDecorator, Terminal, TerminalBorder};
 use runtime::Runtime;
 use serde::Serialize;
-use crate::{AppState, GlobalTerminal, TokioHandle, GAME_SIZE, VIEWPORT_SIZE};
+use crate::{AppState, GamePhase, GlobalTerminal, TokioHandle, GAME_SIZE, VIEWPORT_SIZE};
 use crate::dbs::mongodb::{LoreDatabase, LoreEntry};
 use crate::main_menu::CharacterName;
 use crate::weapon_prediction::bridge::generate_weapon;
@@ -145,10 +145,25 @@ pub fn enter_game(mut query: Query<&mut Terminal, With<GlobalTerminal>>) {
 // Handle input in the InGame state
 pub fn game_input(
     keyboard: Res<ButtonInput<KeyCode>>,
+    phase: Res<State<GamePhase>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
-    if keyboard.just_pressed(KeyCode::Escape) {
-        next_state.set(AppState::MainMenu);
+    match phase.get() {
+        // Esc closes the inventory before it leaves the game
+        GamePhase::Inventory if keyboard.just_pressed(KeyCode::Escape) => {
+            next_phase.set(GamePhase::PlayerTurn);
+        }
+        GamePhase::Inventory if keyboard.just_pressed(KeyCode::KeyI) => {
+            next_phase.set(GamePhase::PlayerTurn);
+        }
+        GamePhase::PlayerTurn if keyboard.just_pressed(KeyCode::KeyI) => {
+            next_phase.set(GamePhase::Inventory);
+        }
+        _ if keyboard.just_pressed(KeyCode::Escape) => {
+            next_state.set(AppState::MainMenu);
+        }
+        _ => {}
     }
 }
 
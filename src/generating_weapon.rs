@@ -13,6 +13,7 @@ use crate::dbs::playerdb::{Database, PlayerDb};
 use crate::dbs::weapondb::WeaponDB;
 use crate::main_menu::{CharacterName, PlayerSaved};
 use crate::player::Player;
+use crate::replay::Playback;
 use crate::weapon_prediction::bridge::{generate_weapon, Weapon};
 
 #[derive(Resource)]
@@ -194,10 +195,12 @@ pub fn save_weapon_after_creation(
     mut saved: ResMut<SavedWeapon>,
     generated_weapon: Res<GeneratedWeapon>,
     psql: Res<Database>,
+    character_name: Res<CharacterName>,
+    playback: Option<Res<Playback>>,
 ) {
 
-    if saved.0 {
-        return; // if weapon is saved already, do nothing
+    if saved.0 || playback.is_some() {
+        return; // if weapon is saved already or comes from a replay, do nothing
     }
 
     let weapon = &generated_weapon.0; //if it is not saved, set weapon to the generated one
@@ -216,10 +219,11 @@ pub fn save_weapon_after_creation(
     };
 
     let db_client = psql.client.clone();
+    let name = character_name.0.clone();
 
     bevy::tasks::IoTaskPool::get().spawn(async move {
         match WeaponDB::create_weapon(
-            db_client,
+            db_client.clone(),
             &record.name,
             record.damage,
             record.weight,
@@ -228,7 +232,13 @@ pub fn save_weapon_after_creation(
             &record.weapon_type,
             record.predicted_price,
         ).await {
-            Ok(saved_weapon) => println!("!!! Saved weapon to DB: {:?}", saved_weapon),
+            Ok(saved_weapon) => {
+                println!("!!! Saved weapon to DB: {:?}", saved_weapon);
+                // Keep it around for later runs
+                if let Err(e) = WeaponDB::add_to_inventory(db_client, saved_weapon.id, &name).await {
+                    eprintln!(">>X<< Failed to add weapon to inventory: {}", e);
+                }
+            }
             Err(e) => eprintln!(">>X<< Failed to save weapon: {}", e),
         }
     }).detach();
@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+
+use crate::{
+    combat::{ActorEffect, TargetEvent},
+    map::Map,
+    movement::Position,
+    status::StatusKind,
+    turn_system::{turns_running, TakingATurn, TurnEndSet},
+};
+
+/// Plugin hurting actors that end their turn on dangerous terrain like lava.
+pub struct HazardsPlugin;
+
+impl Plugin for HazardsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, tile_damage_system.after(TurnEndSet).run_if(turns_running));
+    }
+}
+
+/// Burns every actor whose turn just ended on a damaging tile, however it got
+/// there. Armor doesn't help against lava, so the damage is flat.
+fn tile_damage_system(
+    mut ended_turns: RemovedComponents<TakingATurn>,
+    q_positions: Query<&Position>,
+    q_map: Query<&Map>,
+    mut evt_target: EventWriter<TargetEvent>,
+) {
+    let Ok(map) = q_map.single() else {
+        return;
+    };
+
+    for entity in ended_turns.read() {
+        // Despawned actors end their turn too
+        let Ok(pos) = q_positions.get(entity) else {
+            continue;
+        };
+
+        let damage = map.0[pos.0].properties().damage;
+        if damage > 0 {
+            evt_target.write(TargetEvent {
+                actor: entity,
+                target: entity,
+                effect: ActorEffect::Status(StatusKind::Burning, damage),
+            });
+        }
+    }
+}
@@ -0,0 +1,636 @@
+//! Items lying around the dungeon and the player's pack.
+//!
+//! Items are generated with the floor, picked up with [Action::PickUp] and
+//! listed in the [GamePhase::Inventory] overlay, where pressing an item's
+//! number uses it with [Action::UseItem]. Using an item costs a turn and its
+//! effect goes through a [TargetEvent] like any other. The pack is mirrored to
+//! the `inventory_item` table whenever it changes and carried over into the
+//! next run, like the gold in the [Purse](crate::loot::Purse).
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{prelude::*, tasks::IoTaskPool};
+use bevy_ascii_terminal::{color, terminal::Terminal, StringDecorator};
+use bracket_random::prelude::DiceType;
+use rand::Rng;
+use sark_grids::SizedGrid;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::{Action, ActionEvent, ResolveActionsSet},
+    combat::{ActorEffect, ResolveTargetEventsSet, TargetEvent},
+    dbs::{playerdb::InventoryDb, psqldb::Database},
+    main_menu::CharacterName,
+    equipment::{Equipment, Slot},
+    map::{Map, MapTile},
+    map_state::{MapActors, MapObstacles},
+    movement::Position,
+    player::Player,
+    practice::PracticeMode,
+    render::{RenderSystemSet, Renderable},
+    replay::{playing_back, Playback, ReplayRecorder},
+    rng::CombatRng,
+    ui::PrintLog,
+    visibility::MapMemory,
+    AppState, GamePhase, GlobalTerminal,
+};
+use crate::PathMap2dExt::PathMap2dExt;
+
+/// How many items the player can carry.
+pub const INVENTORY_SIZE: usize = 10;
+
+/// HP restored by a healing draught.
+pub const DRAUGHT_HEALING: i32 = 25;
+
+/// Damage dealt by a blast charge, reduced by defense.
+pub const BLAST_DAMAGE: i32 = 15;
+
+/// Tiles a blast charge reaches in every direction.
+pub const BLAST_RADIUS: i32 = 2;
+
+/// Keys using the item stacks listed in the inventory overlay, in order.
+const SLOT_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// Width of the inventory overlay, borders included.
+const OVERLAY_WIDTH: usize = 44;
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PickUpEvent>()
+            .add_event::<UseItemEvent>()
+            .init_resource::<PackPending>()
+            .add_systems(OnEnter(AppState::Lore), empty_inventory)
+            .add_systems(OnEnter(AppState::InGame), load_pack.run_if(not(playing_back)))
+            .add_systems(OnExit(GamePhase::Inventory), redraw_map)
+            .add_systems(
+                Update,
+                (
+                    (pick_up_system, use_item_system).after(ResolveActionsSet),
+                    receive_pack,
+                    save_inventory_system
+                        .after(pick_up_system)
+                        .after(use_item_system)
+                        .after(receive_pack),
+                    inventory_input
+                        .run_if(in_state(GamePhase::Inventory).and(not(playing_back))),
+                    draw_inventory
+                        .after(RenderSystemSet)
+                        .run_if(in_state(GamePhase::Inventory)),
+                )
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(PostUpdate, item_effect_system.in_set(ResolveTargetEventsSet));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ItemKind {
+    HealingDraught,
+    TeleportScroll,
+    BlastCharge,
+    MappingScroll,
+    LeatherArmor,
+    ChainMail,
+    PowerCharm,
+    SwiftCharm,
+    /// A pile of gold, collected into the [Purse](crate::loot::Purse) instead of the pack.
+    Gold(i32),
+}
+
+impl ItemKind {
+    /// Every item that can be carried.
+    pub const ALL: [ItemKind; 8] = [
+        ItemKind::HealingDraught,
+        ItemKind::TeleportScroll,
+        ItemKind::BlastCharge,
+        ItemKind::MappingScroll,
+        ItemKind::LeatherArmor,
+        ItemKind::ChainMail,
+        ItemKind::PowerCharm,
+        ItemKind::SwiftCharm,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemKind::HealingDraught => "healing draught",
+            ItemKind::TeleportScroll => "teleport scroll",
+            ItemKind::BlastCharge => "blast charge",
+            ItemKind::MappingScroll => "mapping scroll",
+            ItemKind::LeatherArmor => "leather armor",
+            ItemKind::ChainMail => "chain mail",
+            ItemKind::PowerCharm => "charm of power",
+            ItemKind::SwiftCharm => "charm of swiftness",
+            ItemKind::Gold(_) => "gold",
+        }
+    }
+
+    /// Where the item is worn, `None` for items that get used up.
+    pub fn slot(&self) -> Option<Slot> {
+        match self {
+            ItemKind::LeatherArmor | ItemKind::ChainMail => Some(Slot::Armor),
+            ItemKind::PowerCharm | ItemKind::SwiftCharm => Some(Slot::Trinket),
+            _ => None,
+        }
+    }
+
+    /// How often the item turns up compared to the others.
+    fn weight(&self) -> u32 {
+        match self.slot() {
+            Some(_) => 1,
+            None => 3,
+        }
+    }
+
+    /// Reads a name made by [ItemKind::name], as stored in the database.
+    pub fn from_name(name: &str) -> Option<ItemKind> {
+        ItemKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub fn glyph(&self) -> char {
+        match self {
+            ItemKind::HealingDraught => '!',
+            ItemKind::TeleportScroll | ItemKind::MappingScroll => '?',
+            ItemKind::BlastCharge => '*',
+            ItemKind::LeatherArmor | ItemKind::ChainMail => '[',
+            ItemKind::PowerCharm | ItemKind::SwiftCharm => '"',
+            ItemKind::Gold(_) => '$',
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            ItemKind::HealingDraught => Color::from(color::LIGHT_GREEN),
+            ItemKind::TeleportScroll => Color::from(color::CYAN),
+            ItemKind::BlastCharge => Color::from(color::DARK_ORANGE),
+            ItemKind::MappingScroll => Color::from(color::YELLOW),
+            ItemKind::LeatherArmor => Color::from(color::DARK_ORANGE),
+            ItemKind::ChainMail => Color::from(color::LIGHT_GRAY),
+            ItemKind::PowerCharm => Color::from(color::RED),
+            ItemKind::SwiftCharm => Color::from(color::CYAN),
+            ItemKind::Gold(_) => Color::from(color::YELLOW),
+        }
+    }
+
+    /// Completes "You ..." when the item is used.
+    pub fn used(&self) -> &'static str {
+        match self {
+            ItemKind::HealingDraught => "drink the healing draught",
+            ItemKind::TeleportScroll => "read the teleport scroll and the world lurches",
+            ItemKind::BlastCharge => "set off the blast charge",
+            ItemKind::MappingScroll => "read the mapping scroll, the floor's layout fills your mind",
+            ItemKind::LeatherArmor => "strap on the leather armor",
+            ItemKind::ChainMail => "pull the chain mail over your head",
+            ItemKind::PowerCharm => "put on the charm of power",
+            ItemKind::SwiftCharm => "put on the charm of swiftness",
+            ItemKind::Gold(_) => "count the gold",
+        }
+    }
+
+    /// Any item, things to wear being rarer than things to use up.
+    pub fn random(rng: &mut impl Rng) -> ItemKind {
+        let total: u32 = ItemKind::ALL.iter().map(ItemKind::weight).sum();
+        let mut roll = rng.random_range(0..total);
+        for kind in ItemKind::ALL {
+            if roll < kind.weight() {
+                return kind;
+            }
+            roll -= kind.weight();
+        }
+        unreachable!("the roll is below the total weight")
+    }
+}
+
+/// An item lying on the floor.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Item(pub ItemKind);
+
+#[derive(Debug, Bundle)]
+pub struct ItemBundle {
+    pub item: Item,
+    pub position: Position,
+    pub renderable: Renderable,
+    pub name: Name,
+}
+
+impl ItemBundle {
+    pub fn new(kind: ItemKind, position: IVec2) -> Self {
+        Self {
+            item: Item(kind),
+            position: Position(position),
+            renderable: Renderable {
+                fg_color: kind.color(),
+                bg_color: Color::BLACK,
+                glyph: kind.glyph(),
+            },
+            name: Name::new(kind.name()),
+        }
+    }
+}
+
+/// The items the player carries, in the order they were picked up.
+#[derive(Component, Default, Debug, Clone)]
+pub struct Inventory(pub Vec<ItemKind>);
+
+impl Inventory {
+    pub fn is_full(&self) -> bool {
+        self.0.len() >= INVENTORY_SIZE
+    }
+
+    /// Each kind of item carried and how many of it, in the order they were first picked up.
+    pub fn stacks(&self) -> Vec<(ItemKind, u32)> {
+        let mut stacks: Vec<(ItemKind, u32)> = Vec::new();
+        for kind in self.0.iter() {
+            match stacks.iter_mut().find(|(k, _)| k == kind) {
+                Some((_, count)) => *count += 1,
+                None => stacks.push((*kind, 1)),
+            }
+        }
+        stacks
+    }
+}
+
+/// An actor picks up the item at its feet, checked by the action resolver.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PickUpEvent {
+    pub actor: Entity,
+    pub item: Entity,
+}
+
+/// An actor uses up one of its items, checked by the action resolver.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct UseItemEvent {
+    pub actor: Entity,
+    pub kind: ItemKind,
+}
+
+/// The pack read from `inventory_item`, waiting to be put in the [Inventory].
+#[derive(Resource, Default)]
+struct PackPending {
+    items: Arc<Mutex<Option<Vec<ItemKind>>>>,
+    /// Saving waits for the stored pack, the emptied one would replace it.
+    loaded: bool,
+}
+
+fn empty_inventory(mut q_inventory: Query<&mut Inventory, With<Player>>) {
+    for mut inventory in q_inventory.iter_mut() {
+        inventory.0.clear();
+    }
+}
+
+fn load_pack(name: Res<CharacterName>, psql: Res<Database>, mut pending: ResMut<PackPending>) {
+    pending.loaded = false;
+    let db_client = psql.client.clone();
+    let name = name.0.clone();
+    let items = Arc::clone(&pending.items);
+
+    IoTaskPool::get().spawn(async move {
+        match InventoryDb::get_items(db_client, &name).await {
+            Ok(stacks) => {
+                let pack = stacks
+                    .iter()
+                    .filter_map(|(item, count)| Some((ItemKind::from_name(item)?, *count)))
+                    .flat_map(|(kind, count)| std::iter::repeat_n(kind, count.max(0) as usize))
+                    .collect();
+                *items.lock().unwrap() = Some(pack);
+            }
+            Err(e) => eprintln!(">>X<< Failed to load inventory: {}", e),
+        }
+    }).detach();
+}
+
+fn receive_pack(
+    mut pending: ResMut<PackPending>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut q_inventory: Query<&mut Inventory, With<Player>>,
+) {
+    let Some(pack) = pending.items.lock().unwrap().take() else {
+        return;
+    };
+    pending.loaded = true;
+
+    if let Ok(mut inventory) = q_inventory.single_mut() {
+        // Anything picked up before the database answered goes on top
+        let found = std::mem::replace(&mut inventory.0, pack.clone());
+        inventory.0.extend(found);
+        inventory.0.truncate(INVENTORY_SIZE);
+    }
+    // A replay starts with the same pack
+    recorder.0.pack = pack;
+}
+
+fn pick_up_system(
+    mut commands: Commands,
+    mut evt_pick_up: EventReader<PickUpEvent>,
+    q_items: Query<&Item>,
+    mut q_inventory: Query<&mut Inventory>,
+    q_player: Query<(), With<Player>>,
+    mut log: ResMut<PrintLog>,
+) {
+    for ev in evt_pick_up.read() {
+        let (Ok(item), Ok(mut inventory)) = (q_items.get(ev.item), q_inventory.get_mut(ev.actor)) else {
+            continue;
+        };
+        // Goes in the purse, see loot::collect_gold_system
+        if let ItemKind::Gold(_) = item.0 {
+            continue;
+        }
+
+        inventory.0.push(item.0);
+        commands.entity(ev.item).despawn();
+
+        if q_player.contains(ev.actor) {
+            log.push(format!("You pick up a {}.", item.0.name()));
+        }
+    }
+}
+
+fn use_item_system(
+    mut evt_use: EventReader<UseItemEvent>,
+    mut evt_target: EventWriter<TargetEvent>,
+    mut q_inventory: Query<(&mut Inventory, &Position)>,
+    q_player: Query<(), With<Player>>,
+    actors: Res<MapActors>,
+    mut log: ResMut<PrintLog>,
+) {
+    for ev in evt_use.read() {
+        let Ok((mut inventory, pos)) = q_inventory.get_mut(ev.actor) else {
+            continue;
+        };
+        let Some(index) = inventory.0.iter().position(|kind| *kind == ev.kind) else {
+            continue;
+        };
+        inventory.0.remove(index);
+
+        if q_player.contains(ev.actor) {
+            log.push(format!("You {}.", ev.kind.used()));
+        }
+
+        let mut target = |target: Entity, effect: ActorEffect| {
+            evt_target.write(TargetEvent {
+                actor: ev.actor,
+                target,
+                effect,
+            });
+        };
+
+        match ev.kind {
+            ItemKind::HealingDraught => target(ev.actor, ActorEffect::Heal(DRAUGHT_HEALING)),
+            ItemKind::TeleportScroll => target(ev.actor, ActorEffect::Teleport),
+            ItemKind::MappingScroll => target(ev.actor, ActorEffect::RevealMap),
+            // Worn instead, see equipment::change_equipment_system
+            ItemKind::LeatherArmor | ItemKind::ChainMail | ItemKind::PowerCharm | ItemKind::SwiftCharm => {}
+            ItemKind::Gold(_) => {}
+            ItemKind::BlastCharge => {
+                for x in -BLAST_RADIUS..=BLAST_RADIUS {
+                    for y in -BLAST_RADIUS..=BLAST_RADIUS {
+                        let p = pos.0 + IVec2::new(x, y);
+                        if !actors.0.in_bounds(p) {
+                            continue;
+                        }
+                        match actors.0[p] {
+                            Some(hit) if hit != ev.actor => target(hit, ActorEffect::Damage(BLAST_DAMAGE)),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Resolves the [ActorEffect]s that move actors around or change what they know of the map.
+fn item_effect_system(
+    mut target_events: EventReader<TargetEvent>,
+    mut q_targets: Query<(&mut Position, Option<&mut MapMemory>)>,
+    mut q_map: Query<&mut Map>,
+    mut obstacles: ResMut<MapObstacles>,
+    mut actors: ResMut<MapActors>,
+    mut rng: ResMut<CombatRng>,
+) {
+    let Ok(mut map) = q_map.single_mut() else {
+        return;
+    };
+
+    for ev in target_events.read() {
+        let Ok((mut pos, memory)) = q_targets.get_mut(ev.target) else {
+            continue;
+        };
+
+        match ev.effect {
+            ActorEffect::Teleport => {
+                let grid = obstacles.0.grid_mut();
+                let free: Vec<IVec2> = map
+                    .0
+                    .iter()
+                    .enumerate()
+                    .map(|(i, _)| map.0.transform_itl(i))
+                    .filter(|p| map.0[*p] == MapTile::Floor && !grid[*p])
+                    .collect();
+                if free.is_empty() {
+                    continue;
+                }
+
+                let next = free[rng.roll(DiceType::new(1, free.len() as i32, -1)) as usize];
+                let curr = pos.0;
+                pos.0 = next;
+                actors.0[curr] = None;
+                actors.0[next] = Some(ev.target);
+                grid[curr] = false;
+                grid[next] = true;
+            }
+            ActorEffect::RevealMap => {
+                let Some(mut memory) = memory else {
+                    continue;
+                };
+                for (i, remembered) in memory.0.iter_mut().enumerate() {
+                    *remembered |= worth_mapping(&map, map.0.transform_itl(i));
+                }
+                // Memory isn't watched by the renderer
+                map.set_changed();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Anything but the solid rock behind the walls.
+fn worth_mapping(map: &Map, p: IVec2) -> bool {
+    if map.0[p] != MapTile::Wall {
+        return true;
+    }
+
+    (-1..=1).any(|x| {
+        (-1..=1).any(|y| {
+            let n = p + IVec2::new(x, y);
+            map.0.in_bounds(n) && map.0[n] != MapTile::Wall
+        })
+    })
+}
+
+/// Uses the item stack picked by number or changes equipment, then closes the overlay.
+fn inventory_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    q_player: Query<(Entity, &Inventory, &Equipment), With<Player>>,
+    mut evt_action: EventWriter<ActionEvent>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+) {
+    let Ok((player, inventory, equipment)) = q_player.single() else {
+        return;
+    };
+
+    let action = if let Some(slot) = SLOT_KEYS.iter().position(|key| keyboard.just_pressed(*key)) {
+        match inventory.stacks().get(slot) {
+            Some((kind, _)) => Action::UseItem(*kind),
+            None => return,
+        }
+    } else if keyboard.just_pressed(KeyCode::KeyW) {
+        match equipment.next_weapon() {
+            Some(index) => Action::Wield(index),
+            None => return,
+        }
+    } else if keyboard.just_pressed(KeyCode::KeyA) {
+        Action::TakeOff(Slot::Armor)
+    } else if keyboard.just_pressed(KeyCode::KeyT) {
+        Action::TakeOff(Slot::Trinket)
+    } else {
+        return;
+    };
+
+    // Resolved once the phase is back to the player's turn
+    evt_action.write(ActionEvent {
+        actor: player,
+        action,
+    });
+    next_phase.set(GamePhase::PlayerTurn);
+}
+
+fn save_inventory_system(
+    q_inventory: Query<&Inventory, (With<Player>, Changed<Inventory>)>,
+    name: Res<CharacterName>,
+    psql: Res<Database>,
+    pending: Res<PackPending>,
+    playback: Option<Res<Playback>>,
+    practice: Res<PracticeMode>,
+) {
+    let Ok(inventory) = q_inventory.single() else {
+        return;
+    };
+    // Replays don't own the character's pack, practice runs can be undone
+    if playback.is_some() || practice.0 || !pending.loaded {
+        return;
+    }
+
+    let items: Vec<(String, i32)> = inventory
+        .stacks()
+        .into_iter()
+        .map(|(kind, count)| (kind.name().to_string(), count as i32))
+        .collect();
+
+    let db_client = psql.client.clone();
+    let name = name.0.clone();
+    IoTaskPool::get().spawn(async move {
+        if let Err(e) = InventoryDb::save_items(db_client, &name, &items).await {
+            eprintln!(">>X<< Failed to save inventory: {}", e);
+        }
+    }).detach();
+}
+
+/// The overlay is drawn over the map, which has to be drawn again once it's closed.
+fn redraw_map(mut q_map: Query<&mut Map>) {
+    if let Ok(mut map) = q_map.single_mut() {
+        map.set_changed();
+    }
+}
+
+fn draw_inventory(
+    q_inventory: Query<(&Inventory, &Equipment), With<Player>>,
+    mut q_term: Query<&mut Terminal, With<GlobalTerminal>>,
+) {
+    let (Ok((inventory, equipment)), Ok(mut term)) = (q_inventory.single(), q_term.single_mut()) else {
+        return;
+    };
+
+    let inner = OVERLAY_WIDTH - 2;
+    let x = (term.width() as usize).saturating_sub(OVERLAY_WIDTH) as i32 / 2;
+    let mut y = 4;
+
+    let title = format!(" Inventory {}/{} ", inventory.0.len(), INVENTORY_SIZE);
+    term.put_string([x, y], format!("+{:-^inner$}+", title).fg(color::WHITE));
+
+    let worn = |kind: Option<ItemKind>| kind.map_or("none", |k| k.name());
+    let weapon = equipment.weapon().map_or_else(
+        || "bare hands".to_string(),
+        |w| format!("{} ({}d{})", w.name, w.dice.n_dice, w.dice.die_type),
+    );
+    let mut lines = vec![
+        (format!(" Weapon:  {}", weapon), color::WHITE),
+        (format!(" Armor:   {}", worn(equipment.armor)), color::WHITE),
+        (format!(" Trinket: {}", worn(equipment.trinket)), color::WHITE),
+        (String::new(), color::WHITE),
+    ];
+
+    let stacks = inventory.stacks();
+    let mut pack: Vec<_> = stacks
+        .iter()
+        .zip(1..)
+        .map(|((kind, count), slot)| {
+            let line = match count {
+                1 => format!(" {}) {}", slot, kind.name()),
+                _ => format!(" {}) {} x{}", slot, kind.name(), count),
+            };
+            (line, color::WHITE)
+        })
+        .collect();
+    if pack.is_empty() {
+        pack.push((" You aren't carrying anything.".to_string(), color::GRAY));
+    }
+    lines.append(&mut pack);
+    lines.push((String::new(), color::WHITE));
+    lines.push((" [1-9] Use/wear  [W] Next weapon".to_string(), color::GREEN));
+    lines.push((" [A]/[T] Take off armor/trinket".to_string(), color::GREEN));
+    lines.push((" [I/Esc] Close".to_string(), color::GREEN));
+
+    for (line, line_color) in lines {
+        y += 1;
+        term.put_string([x, y], "|".fg(color::WHITE));
+        term.put_string([x + 1, y], format!("{:<inner$.inner$}", line).fg(line_color));
+        term.put_string([x + OVERLAY_WIDTH as i32 - 1, y], "|".fg(color::WHITE));
+    }
+
+    term.put_string([x, y + 1], format!("+{:-<inner$}+", "").fg(color::WHITE));
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Inventory, ItemKind};
+
+    #[test]
+    fn inventory_stacks_items_by_kind() {
+        let inventory = Inventory(vec![
+            ItemKind::BlastCharge,
+            ItemKind::HealingDraught,
+            ItemKind::BlastCharge,
+        ]);
+
+        assert_eq!(
+            vec![(ItemKind::BlastCharge, 2), (ItemKind::HealingDraught, 1)],
+            inventory.stacks()
+        );
+
+        for kind in ItemKind::ALL {
+            assert_eq!(Some(kind), ItemKind::from_name(kind.name()));
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Loot dropped by killed monsters and the player's gold.
+//!
+//! Every monster carries a [LootTable], rolled with the [LootRng] when it
+//! dies. Gold lies around as [ItemKind::Gold] and is collected by walking
+//! over it, the player's [Purse] mirrors the `inventory.gold` column.
+
+use std::{
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{prelude::*, tasks::IoTaskPool};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    action::ResolveActionsSet,
+    combat::DeathSystemSet,
+    dbs::{playerdb::InventoryDb, psqldb::Database},
+    item::{Item, ItemBundle, ItemKind, PickUpEvent},
+    main_menu::CharacterName,
+    movement::Position,
+    player::Player,
+    practice::PracticeMode,
+    replay::Playback,
+    rng::LootRng,
+    turn_system::turns_running,
+    ui::PrintLog,
+    AppState,
+};
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DropLootEvent>()
+            .init_resource::<GoldPending>()
+            .add_systems(OnEnter(AppState::Lore), empty_purse)
+            .add_systems(OnEnter(AppState::InGame), load_gold)
+            .add_systems(
+                PostUpdate,
+                drop_loot_system
+                    .after(DeathSystemSet)
+                    .run_if(turns_running),
+            )
+            .add_systems(
+                Update,
+                (
+                    walk_over_gold_system.after(ResolveActionsSet),
+                    collect_gold_system.after(walk_over_gold_system),
+                )
+                    .run_if(turns_running),
+            )
+            .add_systems(Update, receive_gold.run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// What a monster can drop when it dies.
+#[derive(Component, Debug, Clone, Default, Deserialize)]
+pub struct LootTable {
+    /// Chance in percent to drop gold.
+    #[serde(default)]
+    pub gold_chance: u32,
+    /// How much gold is dropped.
+    #[serde(default)]
+    pub gold: Range<i32>,
+    /// Items and the chance in percent for each of them to drop.
+    #[serde(default)]
+    pub items: Vec<(ItemKind, u32)>,
+}
+
+impl LootTable {
+    /// Everything the table drops this time, gold included.
+    pub fn roll(&self, rng: &mut impl Rng) -> Vec<ItemKind> {
+        let mut drops = Vec::new();
+
+        if !self.gold.is_empty() && rng.random_range(0..100) < self.gold_chance {
+            drops.push(ItemKind::Gold(rng.random_range(self.gold.clone())));
+        }
+        for (kind, chance) in self.items.iter() {
+            if rng.random_range(0..100) < *chance {
+                drops.push(*kind);
+            }
+        }
+        drops
+    }
+
+    /// Monsters further down carry more gold, a quarter more every four floors.
+    pub fn scale_to_depth(&mut self, depth: u32) {
+        let scale = |gold: i32| gold + gold * depth as i32 / 16;
+        self.gold = scale(self.gold.start)..scale(self.gold.end);
+    }
+}
+
+/// The player's gold.
+#[derive(Component, Default, Debug)]
+pub struct Purse(pub i32);
+
+/// A killed actor's loot table is rolled at `position`.
+#[derive(Event, Debug)]
+pub struct DropLootEvent {
+    pub position: IVec2,
+    pub table: LootTable,
+}
+
+/// Gold read from `inventory.gold`, waiting to be put in the [Purse].
+#[derive(Resource, Default)]
+struct GoldPending(Arc<Mutex<Option<i32>>>);
+
+fn empty_purse(mut q_purse: Query<&mut Purse, With<Player>>) {
+    for mut purse in q_purse.iter_mut() {
+        purse.0 = 0;
+    }
+}
+
+fn load_gold(name: Res<CharacterName>, psql: Res<Database>, pending: Res<GoldPending>) {
+    let db_client = psql.client.clone();
+    let name = name.0.clone();
+    let pending = Arc::clone(&pending.0);
+
+    IoTaskPool::get().spawn(async move {
+        match InventoryDb::get_gold(db_client, &name).await {
+            Ok(gold) => *pending.lock().unwrap() = Some(gold),
+            Err(e) => eprintln!(">>X<< Failed to load gold: {}", e),
+        }
+    }).detach();
+}
+
+fn receive_gold(pending: Res<GoldPending>, mut q_purse: Query<&mut Purse, With<Player>>) {
+    let Some(gold) = pending.0.lock().unwrap().take() else {
+        return;
+    };
+    if let Ok(mut purse) = q_purse.single_mut() {
+        // Anything found before the database answered is already saved on top
+        purse.0 += gold;
+    }
+}
+
+fn drop_loot_system(
+    mut commands: Commands,
+    mut evt_drop: EventReader<DropLootEvent>,
+    mut rng: ResMut<LootRng>,
+) {
+    for ev in evt_drop.read() {
+        for kind in ev.table.roll(&mut rng.0) {
+            commands.spawn(ItemBundle::new(kind, ev.position));
+        }
+    }
+}
+
+fn walk_over_gold_system(
+    q_player: Query<(Entity, &Position), (With<Player>, Changed<Position>)>,
+    q_items: Query<(Entity, &Item, &Position), Without<Player>>,
+    mut evt_pick_up: EventWriter<PickUpEvent>,
+) {
+    let Ok((player, pos)) = q_player.single() else {
+        return;
+    };
+
+    for (item, _, _) in q_items
+        .iter()
+        .filter(|(_, item, p)| p.0 == pos.0 && matches!(item.0, ItemKind::Gold(_)))
+    {
+        evt_pick_up.write(PickUpEvent {
+            actor: player,
+            item,
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn collect_gold_system(
+    mut commands: Commands,
+    mut evt_pick_up: EventReader<PickUpEvent>,
+    q_items: Query<&Item>,
+    mut q_purse: Query<&mut Purse>,
+    mut log: ResMut<PrintLog>,
+    name: Res<CharacterName>,
+    psql: Res<Database>,
+    practice: Res<PracticeMode>,
+    playback: Option<Res<Playback>>,
+) {
+    for ev in evt_pick_up.read() {
+        let (Ok(Item(ItemKind::Gold(amount))), Ok(mut purse)) = (q_items.get(ev.item), q_purse.get_mut(ev.actor)) else {
+            continue;
+        };
+        let amount = *amount;
+
+        purse.0 += amount;
+        commands.entity(ev.item).despawn();
+        log.push(format!("You pick up {} gold.", amount));
+
+        // Replays don't own the character's gold, and practice pickups can be undone
+        if playback.is_some() || practice.0 {
+            continue;
+        }
+        let db_client = psql.client.clone();
+        let name = name.0.clone();
+        IoTaskPool::get().spawn(async move {
+            if let Err(e) = InventoryDb::add_gold(db_client, &name, amount).await {
+                eprintln!(">>X<< Failed to save gold: {}", e);
+            }
+        }).detach();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::LootTable;
+    use crate::item::ItemKind;
+
+    #[test]
+    fn loot_tables_roll_by_chance() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let table = LootTable {
+            gold_chance: 100,
+            gold: 5..10,
+            items: vec![(ItemKind::HealingDraught, 100), (ItemKind::ChainMail, 0)],
+        };
+
+        for _ in 0..20 {
+            let drops = table.roll(&mut rng);
+            assert_eq!(2, drops.len());
+            assert!(matches!(drops[0], ItemKind::Gold(5..=9)));
+            assert_eq!(ItemKind::HealingDraught, drops[1]);
+        }
+
+        assert!(LootTable::default().roll(&mut rng).is_empty());
+    }
+}
@@ -22,25 +22,37 @@ use crate::dbs::psqldb::Database;
 use crate::dbs::redisdb::RedisDatabase;
 use crate::dbs::mongodb::LoreDatabase;
 
-use crate::main_menu::{apply_pending_state, draw_player_name_list, player_statistics_input, save_player_after_creation, update_player_name_list, update_player_statistics_ui, CharacterName, PendingState, PlayerNameList, PlayerNameListPending, PlayerSaved, PlayerStatsDisplay, PlayerStatsPending, SelectedPlayer};
+use crate::main_menu::{apply_pending_state, draw_player_name_list, player_statistics_input, save_player_after_creation, update_player_name_list, SeedInput, update_player_statistics_ui, CharacterName, PendingState, PlayerNameList, PlayerNameListPending, PlayerSaved, PlayerStatsDisplay, PlayerStatsPending, SelectedPlayer};
 use crate::player::Player;
 use bevy_async_task::*;
 use tokio::runtime::Runtime;
 use crate::game::poll_lore_save_task;
 
 mod PathMap2dExt;
+mod action;
+mod bestiary;
 mod bundle;
 mod combat;
 mod config;
+mod dungeon;
+mod equipment;
 mod events;
+mod experience;
+mod hazards;
+mod item;
+mod loot;
 mod map;
 mod map_state;
 mod monster;
 mod movement;
 mod player;
+mod practice;
 mod render;
+mod replay;
 mod rng;
+mod run_summary;
 mod shapes;
+mod status;
 mod turn_system;
 mod ui;
 mod visibility;
@@ -72,6 +84,7 @@ enum AppState {
     Splash,
     MainMenu,
     CharacterCreation,
+    SeedEntry,
     SelectPlayer,
     PlayerStatistics,
     SettingsMenu,
@@ -83,10 +96,53 @@ enum AppState {
     InGame,
 }
 
+/// What's going on inside [AppState::InGame]. Turns only advance during
+/// [GamePhase::PlayerTurn] and [GamePhase::MonsterTurn].
+#[derive(SubStates, Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[source(AppState = AppState::InGame)]
+enum GamePhase {
+    /// Waiting for the player to act.
+    #[default]
+    PlayerTurn,
+    /// Monsters act until the player's next turn comes up.
+    MonsterTurn,
+    /// Browsing the inventory.
+    Inventory,
+    /// Picking a stat to raise after gaining a level.
+    LevelUp,
+    /// The player died, the run summary is shown.
+    GameOver,
+    /// The player made it through the last floor, the run summary is shown.
+    Victory,
+}
+
 #[derive(Resource)]
 struct TokioHandle(Arc<Runtime>);
  fn main() -> Result<(), Error> {
 
+     // Headless floor snapshots, see map::export
+     let mut args = std::env::args().skip(1);
+     if args.next().as_deref() == Some(map::export::EXPORT_COMMAND) {
+         if let Err(e) = map::export::run(args) {
+             eprintln!("{e}");
+             std::process::exit(1);
+         }
+         return Ok(());
+     }
+
+     // Play a recorded run back, see replay
+     let mut args = std::env::args().skip(1);
+     let replay = match (args.next().as_deref(), args.next()) {
+         (Some("--replay"), Some(path)) => match replay::Replay::load(std::path::Path::new(&path)) {
+             Ok(replay) => Some(replay::PendingReplay(replay)),
+             Err(e) => {
+                 eprintln!("{e}");
+                 std::process::exit(1);
+             }
+         },
+         _ => None,
+     };
+
      let rt = Runtime::new().expect("Failed to create Tokio runtime");
      let rt_handle = Arc::new(rt);
 
@@ -100,7 +156,12 @@ struct TokioHandle(Arc<Runtime>);
          .block_on(LoreDatabase::new("mongodb://localhost:60000"))
          .expect("MongoDB init failed");
 
-    App::new()
+    let mut app = App::new();
+    if let Some(replay) = replay {
+        app.insert_resource(replay);
+    }
+
+    app
         .insert_resource(TokioHandle(rt_handle.clone()))
         // Standard Bevy and ASCII-terminal plugins
         .add_plugins((DefaultPlugins, TerminalPlugins))
@@ -109,17 +170,30 @@ struct TokioHandle(Arc<Runtime>);
         // PLUGINS FOR THE GAME / INGAME STATE EXCLUSIVELY
         .add_plugins(player::PlayerPlugin)
         .add_plugins(map::MapGenPlugin)
+        .add_plugins(rng::RngPlugin)
+        .add_plugins(dungeon::DungeonPlugin)
+        .add_plugins(hazards::HazardsPlugin)
         .add_plugins(render::RenderPlugin)
         .add_plugins(events::EventsPlugin)
         .add_plugins(visibility::VisibilityPlugin)
         .add_plugins(map_state::MapStatePlugin)
         .add_plugins(turn_system::TurnSystemPlugin)
+        .add_plugins(action::ActionPlugin)
+        .add_plugins(replay::ReplayPlugin)
+        .add_plugins(practice::PracticePlugin)
+        .add_plugins(run_summary::RunSummaryPlugin)
+        .add_plugins(experience::ExperiencePlugin)
+        .add_plugins(status::StatusPlugin)
+        .add_plugins(equipment::EquipmentPlugin)
+        .add_plugins(item::ItemPlugin)
+        .add_plugins(loot::LootPlugin)
         .add_plugins(monster::MonstersPlugin)
         .add_plugins(combat::CombatPlugin)
         .add_plugins(ui::UiPlugin)
         // GAME PLUGINS END
         .insert_resource(PendingState::default())
         .insert_resource(CharacterName::default())
+        .insert_resource(SeedInput::default())
         .insert_resource(PlayerStatsDisplay::default())
         .insert_resource(PlayerStatsPending::default())
         .insert_resource(PlayerNameList::default())
@@ -135,16 +209,23 @@ struct TokioHandle(Arc<Runtime>);
         .add_systems(Startup, setup_camera)
 
         .add_systems(OnEnter(AppState::Splash), setup_terminal)
-        .add_systems(Update, setup_splash_input.run_if(in_state(AppState::Splash)))
+        .add_systems(Update, setup_splash_input.run_if(in_state(AppState::Splash).and(map::map_assets_settled)))
 
         // Use Default state as MainMenu
         .init_state::<AppState>()
+        .add_sub_state::<GamePhase>()
 
         // Spawn the global terminal camera once at startup
 
         // Menu systems
         .add_systems(OnEnter(AppState::MainMenu), main_menu::enter_menu)
         .add_systems(Update, main_menu::menu_input.run_if(in_state(AppState::MainMenu)))
+        .add_systems(
+            Update,
+            main_menu::enter_menu
+                .after(main_menu::menu_input)
+                .run_if(in_state(AppState::MainMenu).and(resource_changed::<practice::PracticeMode>)),
+        )
         .add_systems(OnExit(AppState::MainMenu), main_menu::exit_menu)
 
         .add_systems(Update, apply_pending_state)
@@ -181,6 +262,10 @@ struct TokioHandle(Arc<Runtime>);
         .add_systems(OnEnter(AppState::CharacterCreation), main_menu::enter_character_creation)
         .add_systems(Update, main_menu::character_creation_input.run_if(in_state(AppState::CharacterCreation)))
 
+        // Run seed entry
+        .add_systems(OnEnter(AppState::SeedEntry), main_menu::enter_seed_entry)
+        .add_systems(Update, main_menu::seed_entry_input.run_if(in_state(AppState::SeedEntry)))
+
         // Lore screen
         .add_systems(OnEnter(AppState::Lore), game::enter_lore)
         .add_systems(Update, game::lore_input.run_if(in_state(AppState::Lore)))
@@ -16,6 +16,8 @@ use crate::dbs::playerdb::PlayerDb;
 use crate::dbs::psqldb::Database;
 use crate::dbs::redisdb::RedisDatabase;
 use crate::player::Player;
+use crate::practice::PracticeMode;
+use crate::rng::{RunSeed, SeedChoice};
 
 #[derive(Component)]
 pub struct MainMenuTag;
@@ -29,6 +31,9 @@ pub struct SoundSettingsTag;
 pub struct CharacterCreationTag;
 #[derive(Resource, Default)]
 pub struct CharacterName(pub String);
+/// Text typed on the seed entry screen.
+#[derive(Resource, Default)]
+pub struct SeedInput(pub String);
 
 #[derive(Resource, Default)]
 pub struct PlayerNameList(pub Vec<String>);
@@ -51,7 +56,11 @@ impl Default for PlayerStatsPending {
 #[derive(Resource, Default)]
 pub struct PendingState(pub Option<AppState>);
 // Spawn the main menu terminal on entering MainMenu state
-pub fn enter_menu(mut query: Query<&mut Terminal, With<GlobalTerminal>>) {
+pub fn enter_menu(
+    mut query: Query<&mut Terminal, With<GlobalTerminal>>,
+    seed: Res<SeedChoice>,
+    practice: Res<PracticeMode>,
+) {
     if let Ok(mut term) = query.single_mut() {
         term.clear();
         term.resize([50, 30]);
@@ -59,7 +68,16 @@ pub fn enter_menu(mut query: Query<&mut Terminal, With<GlobalTerminal>>) {
         term.put_string([0, 5],  "          [1] Descend into Sector DNK-34          ".fg(color::GREEN));
         term.put_string([0, 7],  "          [2] Configure Terminal Settings         ".fg(color::WHITE));
         term.put_string([0, 9],  "          [3] Player Statistics                   ".fg(color::WHITE));
-        term.put_string([0, 11], "          [Esc] Quit                              ".fg(color::WHITE));
+        term.put_string([0, 11], "          [4] Enter Run Seed                      ".fg(color::WHITE));
+        let practice = if practice.0 { "on" } else { "off" };
+        term.put_string([0, 13], format!("          [5] Practice Mode: {:<22}", practice).fg(color::WHITE));
+        term.put_string([0, 15], "          [Esc] Quit                              ".fg(color::WHITE));
+
+        let seed = match seed.0 {
+            Some(seed) => format!("Seed: {}", seed.to_code()),
+            None => "Seed: random".to_string(),
+        };
+        term.put_string([0, 18], format!("{:^50}", seed).fg(color::GRAY));
     } else {
         warn!("Global terminal not found in MAIN MENU");
     }
@@ -72,6 +90,7 @@ pub fn menu_input(
     mut next_state: ResMut<NextState<AppState>>,
     mut exit: ResMut<Events<AppExit>>,
     mut pending: ResMut<PendingState>,
+    mut practice: ResMut<PracticeMode>,
 ) {
     if keyboard.just_pressed(KeyCode::Digit1) {
         pending.0 = Some(AppState::CharacterCreation);
@@ -84,6 +103,13 @@ pub fn menu_input(
         // Go to Player Stats
         pending.0 = Some(AppState::SelectPlayer);
     }
+    if keyboard.just_pressed(KeyCode::Digit4) {
+        pending.0 = Some(AppState::SeedEntry);
+    }
+    if keyboard.just_pressed(KeyCode::Digit5) {
+        // Undo and no permadeath, redrawn by enter_menu
+        practice.0 = !practice.0;
+    }
     if keyboard.just_pressed(KeyCode::Escape) {
         // Quit the app
         exit.send_default();
@@ -154,6 +180,75 @@ pub fn character_creation_input(
     }
 }
 
+fn draw_seed_entry(term: &mut Terminal, input: &str, invalid: bool) {
+    term.clear();
+    term.put_string([0, 2], "==================== RUN  SEED ====================".fg(color::YELLOW));
+    term.put_string([0, 4], "       Enter a seed to replay, empty = random     ".fg(color::WHITE));
+    term.put_string([0, 6],"              >".fg(color::WHITE));
+    term.put_string([15, 6], input.to_string());
+    if invalid {
+        term.put_string([0, 8], "               Not a valid seed code              ".fg(color::RED));
+    }
+    term.put_string([0, 10], "      [Enter] Confirm          [Esc] Back         ".fg(color::GREEN));
+}
+
+pub fn enter_seed_entry(
+    mut query: Query<&mut Terminal, With<GlobalTerminal>>,
+    mut input: ResMut<SeedInput>,
+    seed: Res<SeedChoice>,
+) {
+    input.0 = seed.0.map(|s| s.to_code()).unwrap_or_default();
+    if let Ok(mut term) = query.single_mut() {
+        draw_seed_entry(&mut term, &input.0, false);
+    }
+}
+
+pub fn seed_entry_input(
+    mut evr_kbd: EventReader<KeyboardInput>,
+    mut input: ResMut<SeedInput>,
+    mut seed: ResMut<SeedChoice>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut query: Query<&mut Terminal, With<GlobalTerminal>>,
+    mut invalid: Local<bool>,
+) {
+    let Ok(mut term) = query.single_mut() else {
+        return;
+    };
+
+    for ev in evr_kbd.read() {
+        if ev.state == ButtonState::Released {
+            continue;
+        }
+        match &ev.logical_key {
+            Key::Backspace => {
+                input.0.pop();
+                *invalid = false;
+            }
+            Key::Escape => next_state.set(AppState::MainMenu),
+            Key::Enter => {
+                if input.0.is_empty() {
+                    seed.0 = None;
+                    next_state.set(AppState::MainMenu);
+                } else if let Some(parsed) = RunSeed::parse(&input.0) {
+                    seed.0 = Some(parsed);
+                    next_state.set(AppState::MainMenu);
+                } else {
+                    *invalid = true;
+                }
+            }
+            Key::Character(s) => {
+                if s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') && input.0.len() < 16 {
+                    input.0.push_str(&s.to_uppercase());
+                    *invalid = false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    draw_seed_entry(&mut term, &input.0, *invalid);
+}
+
 #[derive(Resource, Default)]
 pub struct PlayerSaved(bool);
 
@@ -178,6 +273,7 @@ pub fn save_player_after_creation(
             max_hp: max_hp.0,
             defense: defense.0,
             strength: strength.0,
+            level: 1,
             inventory_id: None,
         };
 
@@ -430,6 +526,7 @@ pub fn enter_player_statistics(
                             format!("{:^70}", format!("> {}'s statistics <", data.player.name)),
                             "".to_string(),
                             format!("Character:  {}", data.player.name),
+                            format!(" Level:  {}", data.player.level),
                             format!(" HP:  {}/MAX HP:  {}", data.player.hp, data.player.max_hp),
                             format!(" Defense:  {}", data.player.defense),
                             format!(" Strength:  {}", data.player.strength),
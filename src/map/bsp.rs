@@ -0,0 +1,193 @@
+//! Binary space partition generator.
+//!
+//! The map is split recursively until the leaves get close to the configured
+//! room size. Every leaf gets exactly one room and sibling subtrees are joined
+//! by a tunnel, so every room on the floor is reachable.
+
+use rand::{prelude::StdRng, Rng};
+use sark_grids::SizedGrid;
+
+use crate::{config::MapGenSettings, shapes::Rect};
+
+use super::{build_room, build_tunnels_between_rooms, Map};
+
+/// Chance of keeping a leaf whole once it's small enough to hold a room.
+const STOP_SPLIT_CHANCE: f64 = 0.3;
+
+pub fn generate_rooms(
+    map: &mut Map,
+    settings: &MapGenSettings,
+    rng: &mut StdRng,
+    rooms: &mut Vec<Rect>,
+) {
+    let width = map.0.width() as i32;
+    let height = map.0.height() as i32;
+
+    // Leave a solid wall around the edge of the map
+    let root = Rect::from_position_size((1, 1), (width - 2, height - 2));
+
+    let min_room = settings.room_size.start.max(1) as i32;
+    let max_room = (settings.room_size.end as i32 - 1).max(min_room);
+
+    build_leaf(map, rng, root, min_room, max_room, rooms);
+}
+
+/// Splits `leaf` or carves a room into it, then links both halves of a split.
+fn build_leaf(
+    map: &mut Map,
+    rng: &mut StdRng,
+    leaf: Rect,
+    min_room: i32,
+    max_room: i32,
+    rooms: &mut Vec<Rect>,
+) {
+    // A room needs a wall on every side of it
+    let min_leaf = min_room + 2;
+    let max_leaf = max_room + 2;
+
+    match split_leaf(rng, &leaf, min_leaf, max_leaf) {
+        Some((a, b)) => {
+            let first_a = rooms.len();
+            build_leaf(map, rng, a, min_room, max_room, rooms);
+            let first_b = rooms.len();
+            build_leaf(map, rng, b, min_room, max_room, rooms);
+
+            // Every leaf holds one room, so both halves are never empty
+            let room_a = rooms[rng.random_range(first_a..first_b)];
+            let room_b = rooms[rng.random_range(first_b..rooms.len())];
+            build_tunnels_between_rooms(map, rng, &room_a, &room_b);
+        }
+        None => {
+            let room = room_in_leaf(rng, &leaf, min_room, max_room);
+            build_room(map, &room);
+            rooms.push(room);
+        }
+    }
+}
+
+fn split_leaf(rng: &mut StdRng, leaf: &Rect, min_leaf: i32, max_leaf: i32) -> Option<(Rect, Rect)> {
+    let w = leaf.width();
+    let h = leaf.height();
+
+    let can_split_x = w >= min_leaf * 2;
+    let can_split_y = h >= min_leaf * 2;
+
+    if !can_split_x && !can_split_y {
+        return None;
+    }
+
+    if w <= max_leaf && h <= max_leaf && rng.random_bool(STOP_SPLIT_CHANCE) {
+        return None;
+    }
+
+    let split_x = match (can_split_x, can_split_y) {
+        (true, false) => true,
+        (false, true) => false,
+        // Prefer cutting across the long side so leaves stay roughly square
+        _ if w * 4 > h * 5 => true,
+        _ if h * 4 > w * 5 => false,
+        _ => rng.random_bool(0.5),
+    };
+
+    let (x, y) = (leaf.min.x, leaf.min.y);
+    if split_x {
+        let cut = rng.random_range(min_leaf..=w - min_leaf);
+        Some((
+            Rect::from_position_size((x, y), (cut, h)),
+            Rect::from_position_size((x + cut, y), (w - cut, h)),
+        ))
+    } else {
+        let cut = rng.random_range(min_leaf..=h - min_leaf);
+        Some((
+            Rect::from_position_size((x, y), (w, cut)),
+            Rect::from_position_size((x, y + cut), (w, h - cut)),
+        ))
+    }
+}
+
+/// A room taking up at least half of the leaf, so the map has little dead space.
+fn room_in_leaf(rng: &mut StdRng, leaf: &Rect, min_room: i32, max_room: i32) -> Rect {
+    let max_w = (leaf.width() - 2).min(max_room).max(1);
+    let max_h = (leaf.height() - 2).min(max_room).max(1);
+    let min_w = (max_w / 2).max(min_room).min(max_w);
+    let min_h = (max_h / 2).max(min_room).min(max_h);
+
+    let w = rng.random_range(min_w..=max_w);
+    let h = rng.random_range(min_h..=max_h);
+
+    let x = leaf.min.x + 1 + rng.random_range(0..=(leaf.width() - 2 - w).max(0));
+    let y = leaf.min.y + 1 + rng.random_range(0..=(leaf.height() - 2 - h).max(0));
+
+    Rect::from_position_size((x, y), (w, h))
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::IVec2;
+    use rand::{prelude::StdRng, SeedableRng};
+    use sark_grids::{Grid, SizedGrid};
+
+    use crate::{config::MapGenSettings, map::{Map, MapTile}};
+
+    use super::generate_rooms;
+
+    fn reachable_floor(map: &Map, start: IVec2) -> usize {
+        let mut seen = vec![false; map.0.tile_count()];
+        let mut open = vec![start];
+        let mut count = 0;
+
+        while let Some(p) = open.pop() {
+            if !map.0.in_bounds(p) || map.0[p] != MapTile::Floor {
+                continue;
+            }
+            let i = map.0.transform_lti(p);
+            if seen[i] {
+                continue;
+            }
+            seen[i] = true;
+            count += 1;
+
+            open.extend([IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y].map(|d| p + d));
+        }
+
+        count
+    }
+
+    #[test]
+    fn every_room_is_connected() {
+        let settings = MapGenSettings::default();
+
+        for seed in 0..20 {
+            let mut map = Map(Grid::new([80, 32]));
+            let mut rooms = Vec::new();
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            generate_rooms(&mut map, &settings, &mut rng, &mut rooms);
+
+            let floor = map.0.iter().filter(|t| **t == MapTile::Floor).count();
+            assert!(rooms.len() > 1);
+            assert_eq!(floor, reachable_floor(&map, rooms[0].center()));
+        }
+    }
+
+    #[test]
+    fn rooms_stay_inside_the_map_border() {
+        let settings = MapGenSettings::default();
+        let mut map = Map(Grid::new([80, 32]));
+        let mut rooms = Vec::new();
+        let mut rng = StdRng::seed_from_u64(5);
+
+        generate_rooms(&mut map, &settings, &mut rng, &mut rooms);
+
+        for room in rooms.iter() {
+            assert!(room.min.x >= 1 && room.min.y >= 1);
+            assert!(room.max.x <= 79 && room.max.y <= 31);
+        }
+
+        for (i, a) in rooms.iter().enumerate() {
+            for b in rooms.iter().skip(i + 1) {
+                assert!(a.iter().all(|p| !b.iter().any(|q| q == p)));
+            }
+        }
+    }
+}
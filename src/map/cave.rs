@@ -0,0 +1,180 @@
+//! Cellular automata cave generator.
+//!
+//! The map starts out as noise and is smoothed until walls clump together
+//! into caverns. Only the largest connected cavern is kept, everything else
+//! is filled back in so the whole floor is reachable.
+
+use bevy::math::IVec2;
+use rand::{prelude::StdRng, Rng};
+use sark_grids::SizedGrid;
+
+use crate::{config::MapGenSettings, shapes::Rect};
+
+use super::{connectivity::floor_regions, Map, MapTile};
+
+/// Chance of an interior tile starting out as a wall.
+const INITIAL_WALL_CHANCE: f64 = 0.45;
+/// Number of smoothing passes over the initial noise.
+const SMOOTHING_STEPS: u32 = 5;
+/// A tile becomes a wall when at least this many of its 8 neighbours are walls.
+const WALL_THRESHOLD: usize = 5;
+/// A tile becomes floor when at most this many of its 8 neighbours are walls.
+const FLOOR_THRESHOLD: usize = 3;
+/// Size of the sectors the cavern is cut into for player and monster placement.
+const SECTOR_SIZE: (i32, i32) = (16, 12);
+
+pub fn generate_caves(
+    map: &mut Map,
+    settings: &MapGenSettings,
+    rng: &mut StdRng,
+    rooms: &mut Vec<Rect>,
+) {
+    let width = map.0.width() as i32;
+    let height = map.0.height() as i32;
+
+    for y in 0..height {
+        for x in 0..width {
+            let p = IVec2::new(x, y);
+            map.0[p] = if is_border(map, p) || rng.random_bool(INITIAL_WALL_CHANCE) {
+                MapTile::Wall
+            } else {
+                MapTile::Floor
+            };
+        }
+    }
+
+    for _ in 0..SMOOTHING_STEPS {
+        smooth(map);
+    }
+
+    keep_largest_region(map);
+
+    let min_floor = (settings.room_size.start * settings.room_size.start) as usize;
+    cave_sectors(map, min_floor, rooms);
+}
+
+fn is_border(map: &Map, p: IVec2) -> bool {
+    p.x == 0 || p.y == 0 || p.x == map.0.width() as i32 - 1 || p.y == map.0.height() as i32 - 1
+}
+
+fn wall_neighbours(map: &Map, p: IVec2) -> usize {
+    let mut count = 0;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            let n = p + IVec2::new(dx, dy);
+            if !map.0.in_bounds(n) || map.0[n] == MapTile::Wall {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+fn smooth(map: &mut Map) {
+    let width = map.0.width() as i32;
+    let height = map.0.height() as i32;
+    let mut next = Vec::with_capacity(map.0.tile_count());
+
+    for y in 0..height {
+        for x in 0..width {
+            let p = IVec2::new(x, y);
+            let walls = wall_neighbours(map, p);
+            let tile = if is_border(map, p) || walls >= WALL_THRESHOLD {
+                MapTile::Wall
+            } else if walls <= FLOOR_THRESHOLD {
+                MapTile::Floor
+            } else {
+                map.0[p]
+            };
+            next.push((p, tile));
+        }
+    }
+
+    for (p, tile) in next {
+        map.0[p] = tile;
+    }
+}
+
+/// Fills in every cavern except the largest one.
+fn keep_largest_region(map: &mut Map) {
+    let mut regions = floor_regions(map);
+    regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+
+    for region in regions.iter().skip(1) {
+        for p in region {
+            map.0[*p] = MapTile::Wall;
+        }
+    }
+}
+
+/// Cuts the cavern into sectors and returns the floor bounds of each one as a
+/// room, so player and monster placement work the same as on room layouts.
+fn cave_sectors(map: &Map, min_floor: usize, rooms: &mut Vec<Rect>) {
+    let width = map.0.width() as i32;
+    let height = map.0.height() as i32;
+
+    for sy in (0..height).step_by(SECTOR_SIZE.1 as usize) {
+        for sx in (0..width).step_by(SECTOR_SIZE.0 as usize) {
+            let mut min = IVec2::MAX;
+            let mut max = IVec2::MIN;
+            let mut floor = 0;
+
+            for y in sy..(sy + SECTOR_SIZE.1).min(height) {
+                for x in sx..(sx + SECTOR_SIZE.0).min(width) {
+                    let p = IVec2::new(x, y);
+                    if map.0[p] == MapTile::Floor {
+                        min = min.min(p);
+                        max = max.max(p);
+                        floor += 1;
+                    }
+                }
+            }
+
+            if floor >= min_floor {
+                rooms.push(Rect::from_extents(min.into(), (max + IVec2::ONE).into()));
+            }
+        }
+    }
+
+    // Start in the most open sector
+    if let Some(start) = rooms
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, r)| r.iter().filter(|p| map.0[*p] == MapTile::Floor).count())
+        .map(|(i, _)| i)
+    {
+        rooms.swap(0, start);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{prelude::StdRng, SeedableRng};
+    use sark_grids::Grid;
+
+    use crate::{config::MapGenSettings, map::{connectivity::floor_regions, Map, MapTile}};
+
+    use super::generate_caves;
+
+    #[test]
+    fn only_one_cavern_is_kept() {
+        let settings = MapGenSettings::default();
+
+        for seed in 0..20 {
+            let mut map = Map(Grid::new([80, 32]));
+            let mut rooms = Vec::new();
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            generate_caves(&mut map, &settings, &mut rng, &mut rooms);
+
+            assert_eq!(1, floor_regions(&map).len());
+            assert!(!rooms.is_empty());
+            for room in rooms.iter() {
+                assert!(room.iter().any(|p| map.0[p] == MapTile::Floor));
+            }
+        }
+    }
+}
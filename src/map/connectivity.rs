@@ -0,0 +1,167 @@
+//! Post-generation reachability checks.
+//!
+//! Generators don't always link every room, so once a floor is laid out it is
+//! checked from the player's starting tile and any walkable region that can't
+//! be reached gets a tunnel dug to the nearest reachable tile.
+
+use bevy::math::IVec2;
+use sark_grids::SizedGrid;
+
+use super::{build_horizontal_tunnel, build_vertical_tunnel, Map};
+
+/// The result of [validate_connectivity].
+#[derive(Debug, Default)]
+pub struct ConnectivityReport {
+    /// Number of walkable tiles reachable from the start.
+    pub reachable: usize,
+    /// Every walkable region that can't be reached from the start.
+    pub disconnected: Vec<Vec<IVec2>>,
+}
+
+impl ConnectivityReport {
+    pub fn is_connected(&self) -> bool {
+        self.disconnected.is_empty()
+    }
+}
+
+fn is_walkable(map: &Map, p: IVec2) -> bool {
+    map.0.in_bounds(p) && map.0[p].is_traversable()
+}
+
+/// Groups every walkable tile into 4-way connected regions.
+pub fn floor_regions(map: &Map) -> Vec<Vec<IVec2>> {
+    let mut seen = vec![false; map.0.tile_count()];
+    let mut regions = Vec::new();
+
+    for i in 0..map.0.tile_count() {
+        let start = map.0.transform_itl(i);
+        if seen[i] || !is_walkable(map, start) {
+            continue;
+        }
+
+        let mut region = Vec::new();
+        let mut open = vec![start];
+        seen[i] = true;
+
+        while let Some(p) = open.pop() {
+            region.push(p);
+
+            for dir in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                let n = p + dir;
+                if !is_walkable(map, n) {
+                    continue;
+                }
+                let ni = map.0.transform_lti(n);
+                if !seen[ni] {
+                    seen[ni] = true;
+                    open.push(n);
+                }
+            }
+        }
+
+        regions.push(region);
+    }
+
+    regions
+}
+
+/// Checks that every walkable tile on the map can be reached from `start`.
+pub fn validate_connectivity(map: &Map, start: IVec2) -> ConnectivityReport {
+    let mut report = ConnectivityReport::default();
+
+    for region in floor_regions(map) {
+        if region.contains(&start) {
+            report.reachable = region.len();
+        } else {
+            report.disconnected.push(region);
+        }
+    }
+
+    report
+}
+
+/// Digs tunnels until every walkable region is reachable from `start`.
+///
+/// Returns the report of the repaired map, which is only disconnected
+/// if `start` itself isn't walkable.
+pub fn repair_connectivity(map: &mut Map, start: IVec2) -> ConnectivityReport {
+    loop {
+        let report = validate_connectivity(map, start);
+        if report.is_connected() || report.reachable == 0 {
+            return report;
+        }
+
+        let reachable = floor_regions(map)
+            .into_iter()
+            .find(|region| region.contains(&start))
+            .unwrap_or_default();
+
+        for region in report.disconnected.iter() {
+            // Regions merged by an earlier tunnel this pass are skipped by the next validation
+            let Some((from, to)) = closest_pair(region, &reachable) else {
+                continue;
+            };
+            build_horizontal_tunnel(map, from.x, to.x, from.y);
+            build_vertical_tunnel(map, from.y, to.y, to.x);
+        }
+    }
+}
+
+fn closest_pair(a: &[IVec2], b: &[IVec2]) -> Option<(IVec2, IVec2)> {
+    a.iter()
+        .flat_map(|pa| b.iter().map(move |pb| (*pa, *pb)))
+        .min_by_key(|(pa, pb)| {
+            let d = (*pa - *pb).abs();
+            d.x + d.y
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::IVec2;
+    use sark_grids::Grid;
+
+    use crate::{map::{build_room, Map}, shapes::Rect};
+
+    use super::{repair_connectivity, validate_connectivity};
+
+    #[test]
+    fn reports_unreachable_rooms() {
+        let mut map = Map(Grid::new([40, 20]));
+        let start = Rect::from_position_size((2, 2), (5, 5));
+        build_room(&mut map, &start);
+        build_room(&mut map, &Rect::from_position_size((20, 10), (4, 4)));
+        build_room(&mut map, &Rect::from_position_size((30, 2), (3, 3)));
+
+        let report = validate_connectivity(&map, start.center());
+
+        assert_eq!(25, report.reachable);
+        assert_eq!(2, report.disconnected.len());
+        assert!(!report.is_connected());
+    }
+
+    #[test]
+    fn repair_links_every_room() {
+        let mut map = Map(Grid::new([40, 20]));
+        let start = Rect::from_position_size((2, 2), (5, 5));
+        build_room(&mut map, &start);
+        build_room(&mut map, &Rect::from_position_size((20, 10), (4, 4)));
+        build_room(&mut map, &Rect::from_position_size((30, 2), (3, 3)));
+
+        let report = repair_connectivity(&mut map, start.center());
+
+        assert!(report.is_connected());
+        assert!(report.reachable > 25 + 16 + 9);
+    }
+
+    #[test]
+    fn unwalkable_start_is_reported() {
+        let mut map = Map(Grid::new([10, 10]));
+        build_room(&mut map, &Rect::from_position_size((2, 2), (3, 3)));
+
+        let report = repair_connectivity(&mut map, IVec2::new(8, 8));
+
+        assert_eq!(0, report.reachable);
+        assert_eq!(1, report.disconnected.len());
+    }
+}
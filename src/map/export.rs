@@ -0,0 +1,342 @@
+//! Headless floor snapshots.
+//!
+//! Runs [MapGenerator] outside of the Bevy app and writes the result as ASCII
+//! and as a PNG, so generator changes can be looked at in code review and
+//! checked against golden files:
+//!
+//! ```text
+//! cargo run -- export-map --seed 3W5E11264SGSF --depth 2 --algorithm cave --out snapshots
+//! ```
+//!
+//! The ASCII snapshot uses the in-game glyphs, with `@` for the player's start,
+//! monster glyphs for monster spawns and `$` for vault item spawns. The PNG
+//! uses the in-game colors, one block per tile.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{color::ColorToPacked, prelude::*};
+use bevy_ascii_terminal::Tile;
+use sark_grids::SizedGrid;
+use thiserror::Error;
+
+use crate::{
+    bestiary::{Bestiary, BESTIARY_FILE_NAME},
+    config::{MapAlgorithm, MapGenSettings, MAP_SETTINGS_FILE_NAME},
+    rng::RunSeed,
+    GAME_SIZE,
+};
+
+use super::{
+    floor_rng,
+    vault::{parse_vault, Vault, VaultParseError, VAULT_FOLDER},
+    MapGenerator,
+};
+
+/// First argument that switches the game binary into exporting.
+pub const EXPORT_COMMAND: &str = "export-map";
+
+/// Size in pixels of one tile in the PNG snapshot.
+const PNG_CELL_SIZE: u32 = 6;
+
+const USAGE: &str = "usage: export-map [--seed CODE] [--depth N] [--algorithm rooms|bsp|cave] [--settings FILE] [--out DIR]";
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("{0}\n{USAGE}")]
+    Usage(String),
+    #[error("Error reading {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Error parsing {path}: {source}")]
+    Settings {
+        path: String,
+        #[source]
+        source: ron::error::SpannedError,
+    },
+    #[error("Error parsing {path}: {source}")]
+    Bestiary {
+        path: String,
+        #[source]
+        source: ron::error::SpannedError,
+    },
+    #[error("Error parsing {path}: {source}")]
+    Vault {
+        path: String,
+        #[source]
+        source: VaultParseError,
+    },
+    #[error("Error writing {path}: {source}")]
+    Png {
+        path: String,
+        #[source]
+        source: image::ImageError,
+    },
+}
+
+#[derive(Debug)]
+pub struct ExportOptions {
+    pub settings: PathBuf,
+    /// The run seed, as shown in game.
+    pub seed: RunSeed,
+    /// Overrides the algorithm from the settings file.
+    pub algorithm: Option<MapAlgorithm>,
+    pub depth: u32,
+    pub out: PathBuf,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            settings: Path::new("assets").join(MAP_SETTINGS_FILE_NAME),
+            seed: RunSeed(0),
+            algorithm: None,
+            depth: 0,
+            out: PathBuf::from("."),
+        }
+    }
+}
+
+/// Parses the arguments following [EXPORT_COMMAND].
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<ExportOptions, ExportError> {
+    let mut options = ExportOptions::default();
+    let mut args = args.into_iter();
+
+    while let Some(flag) = args.next() {
+        let Some(value) = args.next() else {
+            return Err(ExportError::Usage(format!("missing value for {flag}")));
+        };
+        let invalid = || ExportError::Usage(format!("invalid value for {flag}: {value}"));
+
+        match flag.as_str() {
+            "--seed" => options.seed = RunSeed::parse(&value).ok_or_else(invalid)?,
+            "--depth" => options.depth = value.parse().map_err(|_| invalid())?,
+            "--algorithm" => {
+                options.algorithm = Some(match value.to_lowercase().as_str() {
+                    "rooms" => MapAlgorithm::Rooms,
+                    "bsp" => MapAlgorithm::Bsp,
+                    "cave" => MapAlgorithm::Cave,
+                    _ => return Err(invalid()),
+                })
+            }
+            "--settings" => options.settings = PathBuf::from(value),
+            "--out" => options.out = PathBuf::from(value),
+            _ => return Err(ExportError::Usage(format!("unknown argument {flag}"))),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Generates the floor described by `args` and writes its snapshots.
+pub fn run(args: impl IntoIterator<Item = String>) -> Result<(), ExportError> {
+    let options = parse_args(args)?;
+
+    let mut settings = load_settings(&options.settings)?;
+    if let Some(algorithm) = options.algorithm {
+        settings.algorithm = algorithm;
+    }
+
+    let vaults = load_vaults(&Path::new("assets").join(VAULT_FOLDER))?;
+    let bestiary = load_bestiary(&Path::new("assets").join(BESTIARY_FILE_NAME))?;
+    let floor = generate(&options.seed, &settings, &vaults, &bestiary, options.depth);
+
+    let stem = format!("floor_{}_{}", options.seed.to_code(), options.depth);
+    let io_error = |path: &Path| {
+        let path = path.display().to_string();
+        move |source| ExportError::Io { path, source }
+    };
+
+    fs::create_dir_all(&options.out).map_err(io_error(&options.out))?;
+
+    let txt = options.out.join(format!("{stem}.txt"));
+    fs::write(&txt, to_ascii(&floor)).map_err(io_error(&txt))?;
+
+    let png = options.out.join(format!("{stem}.png"));
+    to_png(&floor).save(&png).map_err(|source| ExportError::Png {
+        path: png.display().to_string(),
+        source,
+    })?;
+
+    println!("Wrote {} and {}", txt.display(), png.display());
+    Ok(())
+}
+
+pub fn load_settings(path: &Path) -> Result<MapGenSettings, ExportError> {
+    let bytes = fs::read(path).map_err(|source| ExportError::Io {
+        path: path.display().to_string(),
+        source,
+    })?;
+
+    ron::de::from_bytes(&bytes).map_err(|source| ExportError::Settings {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+pub fn load_bestiary(path: &Path) -> Result<Bestiary, ExportError> {
+    let bytes = fs::read(path).map_err(|source| ExportError::Io {
+        path: path.display().to_string(),
+        source,
+    })?;
+
+    ron::de::from_bytes(&bytes).map_err(|source| ExportError::Bestiary {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+/// Reads every `.vault` file in `dir`, sorted by name like [MapGenAssets](super::MapGenAssets::vaults).
+pub fn load_vaults(dir: &Path) -> Result<Vec<Vault>, ExportError> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut vaults = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "vault") {
+            continue;
+        }
+
+        let text = fs::read_to_string(&path).map_err(|source| ExportError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+        let vault = parse_vault(&text).map_err(|source| ExportError::Vault {
+            path: path.display().to_string(),
+            source,
+        })?;
+        vaults.push(vault);
+    }
+
+    vaults.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(vaults)
+}
+
+/// Generates the floor exactly like [generate_floor](super::generate_floor) would in game.
+pub fn generate(
+    seed: &RunSeed,
+    settings: &MapGenSettings,
+    vaults: &[Vault],
+    bestiary: &Bestiary,
+    depth: u32,
+) -> MapGenerator {
+    let mut settings = settings.clone();
+    settings.map_size = GAME_SIZE;
+
+    let vaults: Vec<_> = vaults.iter().collect();
+    let mut rng = floor_rng(seed, &settings, depth);
+
+    MapGenerator::generate(&settings, &mut rng, depth, &vaults, bestiary)
+}
+
+/// The tile drawn at `p`, with spawns drawn over the map.
+fn snapshot_tile(floor: &MapGenerator, p: IVec2) -> Tile {
+    if p == floor.start_position() {
+        return Tile {
+            glyph: '@',
+            fg_color: LinearRgba::from(Color::WHITE),
+            bg_color: LinearRgba::from(Color::BLACK),
+        };
+    }
+
+    if let Some(monster) = floor.monsters.iter().find(|m| m.movable.position.0 == p) {
+        return Tile::from(&monster.movable.renderable);
+    }
+
+    if floor.vault.as_ref().is_some_and(|v| v.items.contains(&p)) {
+        return Tile {
+            glyph: '$',
+            fg_color: LinearRgba::from(Color::srgb(1.0, 0.843, 0.0)),
+            bg_color: LinearRgba::from(Color::BLACK),
+        };
+    }
+
+    Tile::from(floor.map.0[p])
+}
+
+/// One line per map row, top row first.
+pub fn to_ascii(floor: &MapGenerator) -> String {
+    let size = floor.map.0.size().as_ivec2();
+    let mut text = String::with_capacity(((size.x + 1) * size.y) as usize);
+
+    for y in (0..size.y).rev() {
+        for x in 0..size.x {
+            text.push(snapshot_tile(floor, IVec2::new(x, y)).glyph);
+        }
+        text.push('\n');
+    }
+
+    text
+}
+
+pub fn to_png(floor: &MapGenerator) -> image::RgbImage {
+    let size = floor.map.0.size();
+    let mut img = image::RgbImage::new(size.x * PNG_CELL_SIZE, size.y * PNG_CELL_SIZE);
+
+    for y in 0..size.y {
+        for x in 0..size.x {
+            let tile = snapshot_tile(floor, IVec2::new(x as i32, y as i32));
+            let [r, g, b, _] = Srgba::from(tile.fg_color).to_u8_array();
+
+            // Image rows go top down, the map's y axis points up
+            let top = (size.y - 1 - y) * PNG_CELL_SIZE;
+            for py in top..top + PNG_CELL_SIZE {
+                for px in x * PNG_CELL_SIZE..(x + 1) * PNG_CELL_SIZE {
+                    img.put_pixel(px, py, image::Rgb([r, g, b]));
+                }
+            }
+        }
+    }
+
+    img
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use crate::{config::{MapAlgorithm, MapGenSettings}, rng::RunSeed};
+
+    use super::{generate, load_bestiary, load_vaults, to_ascii};
+
+    /// Compares generated floors against `tests/golden`. Run with `UPDATE_GOLDEN=1`
+    /// to accept a generator change or to write new snapshots.
+    #[test]
+    fn floors_match_golden_snapshots() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let golden = root.join("tests").join("golden");
+        let vaults = load_vaults(&root.join("assets").join("vaults")).unwrap();
+        let bestiary = load_bestiary(&root.join("assets").join("bestiary.ron")).unwrap();
+        let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+        let seed = RunSeed(5);
+
+        for algorithm in [MapAlgorithm::Rooms, MapAlgorithm::Bsp, MapAlgorithm::Cave] {
+            for depth in [0, 2] {
+                let settings = MapGenSettings {
+                    algorithm,
+                    ..Default::default()
+                };
+                let actual = to_ascii(&generate(&seed, &settings, &vaults, &bestiary, depth));
+
+                let path = golden.join(format!("{:?}_{}_{}.txt", algorithm, seed.to_code(), depth).to_lowercase());
+                if update {
+                    fs::create_dir_all(&golden).unwrap();
+                    fs::write(&path, actual).unwrap();
+                    continue;
+                }
+
+                let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+                    panic!("{} can't be read ({e}), run with UPDATE_GOLDEN=1 to write it", path.display())
+                });
+                assert_eq!(expected, actual, "{} is out of date, run with UPDATE_GOLDEN=1 to accept the change", path.display());
+            }
+        }
+    }
+}
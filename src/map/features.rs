@@ -0,0 +1,109 @@
+//! Environmental features scattered over the rooms of a generated floor.
+
+use bevy::math::IVec2;
+use rand::{prelude::StdRng, Rng};
+
+use crate::{config::MapGenSettings, shapes::Rect};
+
+use super::{connectivity, Map, MapTile};
+
+/// Lava only shows up from this depth on.
+const LAVA_MIN_DEPTH: u32 = 1;
+
+/// Splashes blobs of water, lava and rubble over floor tiles in every room but
+/// the first. Rubble that would cut off part of the floor is left out.
+pub fn scatter_features(
+    map: &mut Map,
+    rooms: &[Rect],
+    settings: &MapGenSettings,
+    depth: u32,
+    start: IVec2,
+    rng: &mut StdRng,
+) {
+    if settings.features_per_room.is_empty() {
+        return;
+    }
+
+    for room in rooms.iter().skip(1) {
+        let count = rng.random_range(settings.features_per_room.clone());
+
+        for _ in 0..count {
+            let tile = match rng.random_range(0..10) {
+                0..5 => MapTile::ShallowWater,
+                5..8 => MapTile::Rubble,
+                _ if depth >= LAVA_MIN_DEPTH => MapTile::Lava,
+                _ => MapTile::ShallowWater,
+            };
+
+            let center = IVec2::new(
+                rng.random_range(room.min.x..room.max.x),
+                rng.random_range(room.min.y..room.max.y),
+            );
+            let radius = rng.random_range(1..=2);
+
+            let changed = splash(map, room, center, radius, tile);
+
+            if tile.properties().blocks_movement
+                && !connectivity::validate_connectivity(map, start).is_connected()
+            {
+                for p in changed {
+                    map.0[p] = MapTile::Floor;
+                }
+            }
+        }
+    }
+}
+
+/// Turns the floor tiles of `room` within `radius` of `center` into `tile`.
+fn splash(map: &mut Map, room: &Rect, center: IVec2, radius: i32, tile: MapTile) -> Vec<IVec2> {
+    let mut changed = Vec::new();
+
+    for p in room.iter() {
+        let d = p - center;
+        if d.x * d.x + d.y * d.y <= radius * radius && map.0[p] == MapTile::Floor {
+            map.0[p] = tile;
+            changed.push(p);
+        }
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{prelude::StdRng, SeedableRng};
+    use sark_grids::Grid;
+
+    use crate::{
+        config::MapGenSettings,
+        map::{build_horizontal_tunnel, build_room, connectivity::validate_connectivity, Map},
+        shapes::Rect,
+    };
+
+    use super::scatter_features;
+
+    #[test]
+    fn rubble_never_cuts_off_the_floor() {
+        let settings = MapGenSettings {
+            features_per_room: 4..8,
+            ..Default::default()
+        };
+        let rooms = [
+            Rect::from_position_size((2, 2), (5, 5)),
+            Rect::from_position_size((10, 2), (3, 5)),
+            Rect::from_position_size((16, 2), (5, 5)),
+        ];
+
+        for seed in 0..20 {
+            let mut map = Map(Grid::new([24, 10]));
+            for room in rooms.iter() {
+                build_room(&mut map, room);
+            }
+            build_horizontal_tunnel(&mut map, 4, 18, 4);
+
+            scatter_features(&mut map, &rooms, &settings, 2, rooms[0].center(), &mut StdRng::seed_from_u64(seed));
+
+            assert!(validate_connectivity(&map, rooms[0].center()).is_connected());
+        }
+    }
+}
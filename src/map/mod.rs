@@ -0,0 +1,723 @@
+use bevy::{
+    asset::RecursiveDependencyLoadState,
+    ecs::system::SystemParam,
+    math::IVec2,
+    prelude::*,
+};
+use std::collections::HashSet;
+
+use rand::{prelude::StdRng, Rng, SeedableRng};
+use sark_grids::{Grid, SizedGrid};
+
+use crate::{bestiary::{Bestiary, BestiaryAssets, BestiaryHandle}, config::{self, MapAlgorithm, MapGenSettings, MapGenSettingsHandle, MapGenSettingsLoader}, item::{Item, ItemBundle, ItemKind}, monster::{Monster, MonsterBundle}, movement::Position, player::Player, shapes::Rect, AppState, GAME_SIZE};
+use crate::dungeon::DungeonLevel;
+use crate::rng::{RngStream, RunSeed};
+use crate::player::PlayerSpawnSet;
+use crate::visibility::{MapMemory, MapView};
+
+mod bsp;
+mod cave;
+pub mod connectivity;
+pub mod export;
+mod features;
+pub mod vault;
+
+use vault::{StampedVault, Vault, VaultFolderHandle, VaultLoader};
+
+pub struct MapGenPlugin;
+
+pub const MAP_GEN_SETUP_LABEL: &str = "MAP_GEN_SETUP";
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct MapGenSetupSet;
+impl Plugin for MapGenPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .configure_sets(OnEnter(AppState::Lore), PlayerSpawnSet)
+            .configure_sets(OnEnter(AppState::Lore), MapGenSetupSet.after(PlayerSpawnSet))
+            .init_asset::<MapGenSettings>()
+            .init_asset_loader::<MapGenSettingsLoader>()
+            .init_asset::<Vault>()
+            .init_asset_loader::<VaultLoader>()
+            .add_systems(Startup, (config::load_map_settings, vault::load_vaults))
+            .add_systems(Update, config::log_map_settings_errors)
+            .add_systems(Update, regenerate_on_settings_change.run_if(any_with_component::<Map>))
+            .add_systems(OnEnter(AppState::Lore), setup.in_set(MapGenSetupSet));
+    }
+}
+
+/// The assets a floor is generated from.
+#[derive(SystemParam)]
+pub struct MapGenAssets<'w> {
+    settings_handle: Option<Res<'w, MapGenSettingsHandle>>,
+    settings: Res<'w, Assets<MapGenSettings>>,
+    vaults: Res<'w, Assets<Vault>>,
+    bestiary: BestiaryAssets<'w>,
+    run_seed: Res<'w, RunSeed>,
+}
+
+impl MapGenAssets<'_> {
+    pub fn settings(&self) -> MapGenSettings {
+        config::current_map_settings(self.settings_handle.as_deref(), &self.settings)
+    }
+
+    /// Every loaded vault, sorted by name so generation doesn't depend on load order.
+    pub fn vaults(&self) -> Vec<&Vault> {
+        let mut vaults: Vec<_> = self.vaults.iter().map(|(_, vault)| vault).collect();
+        vaults.sort_by(|a, b| a.name.cmp(&b.name));
+        vaults
+    }
+
+    pub fn bestiary(&self) -> Bestiary {
+        self.bestiary.get()
+    }
+}
+
+/// Whether the settings, vaults and bestiary are done loading, successfully or not.
+/// Floors generated before that would quietly use the fallbacks instead, so the
+/// splash screen waits on this before a run can be started.
+pub fn map_assets_settled(
+    asset_server: Res<AssetServer>,
+    settings: Option<Res<MapGenSettingsHandle>>,
+    vaults: Option<Res<VaultFolderHandle>>,
+    bestiary: Option<Res<BestiaryHandle>>,
+) -> bool {
+    let (Some(settings), Some(vaults), Some(bestiary)) = (settings, vaults, bestiary) else {
+        return false;
+    };
+
+    [settings.0.id().untyped(), vaults.0.id().untyped(), bestiary.0.id().untyped()]
+        .into_iter()
+        .all(|id| {
+            asset_server.is_loaded_with_dependencies(id)
+                || matches!(
+                    asset_server.get_recursive_dependency_load_state(id),
+                    Some(RecursiveDependencyLoadState::Failed(_))
+                )
+        })
+}
+
+fn setup(
+    mut commands: Commands,
+    q_player: Query<(Entity,&Player)>,
+    assets: MapGenAssets,
+    level: Res<DungeonLevel>,
+) {
+    let player = q_player.single().map_or_else(|_|None,|(e,_)|Some(e));
+
+    generate_floor(&mut commands, &assets, player, level.depth);
+}
+
+/// Rebuilds the current floor in place whenever [MapGenSettings] is hot-reloaded.
+fn regenerate_on_settings_change(
+    mut commands: Commands,
+    mut evt_settings: EventReader<AssetEvent<MapGenSettings>>,
+    assets: MapGenAssets,
+    q_map: Query<Entity, With<Map>>,
+    q_monsters: Query<Entity, With<Monster>>,
+    q_items: Query<Entity, With<Item>>,
+    q_player: Query<Entity, With<Player>>,
+    level: Res<DungeonLevel>,
+) {
+    let Some(handle) = assets.settings_handle.as_ref() else {
+        return;
+    };
+
+    let modified = evt_settings
+        .read()
+        .filter(|ev| ev.is_modified(handle.0.id()))
+        .count();
+    if modified == 0 {
+        return;
+    }
+
+    info!("{} changed, regenerating the current floor", config::MAP_SETTINGS_FILE_NAME);
+
+    for entity in q_map.iter().chain(q_monsters.iter()).chain(q_items.iter()) {
+        commands.entity(entity).despawn();
+    }
+
+    generate_floor(&mut commands, &assets, q_player.single().ok(), level.depth);
+}
+
+/// Generates a brand new floor at `depth`, the same depth and assets always give the same floor.
+pub fn generate_floor(commands: &mut Commands, assets: &MapGenAssets, player: Option<Entity>, depth: u32) {
+    let mut settings = assets.settings();
+    settings.map_size = GAME_SIZE;
+
+    let rng = floor_rng(&assets.run_seed, &settings, depth);
+    let entities = MapGenEntities {
+        player,
+    };
+
+    MapGenerator::build(commands, settings, rng, entities, depth, &assets.vaults(), &assets.bestiary());
+}
+/// The random streams a floor is generated from.
+pub struct FloorRng {
+    /// Layout, vaults and terrain features.
+    pub map: StdRng,
+    /// Which monsters spawn and where.
+    pub monsters: StdRng,
+    /// Which items lie around and where.
+    pub items: StdRng,
+}
+
+/// The rngs a floor at `depth` is generated with, derived from the run's seed
+/// unless [MapGenSettings::seed] pins it.
+pub fn floor_rng(run_seed: &RunSeed, settings: &MapGenSettings, depth: u32) -> FloorRng {
+    let seed = settings.seed.map_or(*run_seed, RunSeed);
+
+    FloorRng {
+        map: StdRng::seed_from_u64(seed.stream(RngStream::Map).wrapping_add(depth as u64)),
+        monsters: StdRng::seed_from_u64(seed.stream(RngStream::Monsters).wrapping_add(depth as u64)),
+        items: StdRng::seed_from_u64(seed.stream(RngStream::Items).wrapping_add(depth as u64)),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Hash)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+impl Map {
+    pub fn side_index(&self, side: Side) -> u32 {
+        match side {
+            Side::Left => 0,
+            Side::Top => 0,
+            Side::Right => (self.0.width() - 1) as u32,
+            Side::Bottom => (self.0.height() - 1) as u32,
+        }
+    }
+}
+
+
+/// A tile on the [Map].
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum MapTile {
+    Wall,
+    Floor,
+    /// Leads to the next floor down.
+    StairsDown,
+    /// Leads back to the floor above.
+    StairsUp,
+    /// Blocks sight and movement until something bumps into it.
+    DoorClosed,
+    DoorOpen,
+    ShallowWater,
+    Lava,
+    Rubble,
+}
+
+/// Gameplay rules of a [MapTile], see [MapTile::properties].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileProperties {
+    /// Stops field of view.
+    pub blocks_vision: bool,
+    /// Obstacle for movement and pathfinding.
+    pub blocks_movement: bool,
+    /// Bumping into the tile turns it into this tile instead.
+    pub opens_into: Option<MapTile>,
+    /// Energy spent on top of a normal move when stepping onto the tile.
+    pub move_cost: i32,
+    /// Damage taken by actors ending their turn on the tile, ignoring defense.
+    pub damage: i32,
+}
+
+const OPEN_GROUND: TileProperties = TileProperties {
+    blocks_vision: false,
+    blocks_movement: false,
+    opens_into: None,
+    move_cost: 0,
+    damage: 0,
+};
+
+const SOLID: TileProperties = TileProperties {
+    blocks_vision: true,
+    blocks_movement: true,
+    ..OPEN_GROUND
+};
+
+impl MapTile {
+    pub const fn properties(&self) -> TileProperties {
+        match self {
+            MapTile::Wall => SOLID,
+            MapTile::Floor | MapTile::StairsDown | MapTile::StairsUp | MapTile::DoorOpen => OPEN_GROUND,
+            MapTile::DoorClosed => TileProperties {
+                opens_into: Some(MapTile::DoorOpen),
+                ..SOLID
+            },
+            MapTile::ShallowWater => TileProperties {
+                move_cost: 50,
+                ..OPEN_GROUND
+            },
+            MapTile::Lava => TileProperties {
+                damage: 5,
+                ..OPEN_GROUND
+            },
+            MapTile::Rubble => TileProperties {
+                blocks_movement: true,
+                ..OPEN_GROUND
+            },
+        }
+    }
+
+    /// Whether an actor can get past the tile, either by walking or by opening it.
+    pub const fn is_traversable(&self) -> bool {
+        let properties = self.properties();
+        !properties.blocks_movement || properties.opens_into.is_some()
+    }
+}
+
+impl Default for MapTile {
+    fn default() -> Self {
+        Self::Wall
+    }
+}
+
+#[derive(Component)]
+pub struct Map(pub Grid<MapTile>);
+
+pub struct MapGenEntities {
+    pub player: Option<Entity>,
+    //pub monsters: Vec<MonsterBundle>,
+}
+
+pub struct MapGenerator {
+    pub map: Map,
+    pub rooms: Vec<Rect>,
+    pub vault: Option<StampedVault>,
+    /// Monsters ready to be spawned, positions already set.
+    pub monsters: Vec<MonsterBundle>,
+    /// Items to spawn and where.
+    pub items: Vec<(IVec2, ItemKind)>,
+}
+
+impl MapGenerator {
+    pub fn build(
+        commands: &mut Commands,
+        settings: MapGenSettings,
+        mut rng: FloorRng,
+        entities: MapGenEntities,
+        depth: u32,
+        vaults: &[&Vault],
+        bestiary: &Bestiary,
+    ) {
+        let map = MapGenerator::generate(&settings, &mut rng, depth, vaults, bestiary);
+
+        if let Some(player) = entities.player {
+            map.place_player(commands, player);
+        } else {
+            println!("No player found");
+        }
+
+        for monster in map.monsters {
+            commands.spawn(monster);
+        }
+
+        for (p, kind) in map.items {
+            commands.spawn(ItemBundle::new(kind, p));
+        }
+
+        commands.spawn(map.map);
+    }
+
+    /// Lays out a whole floor without touching the ECS, so it can also be
+    /// run headless (see [export]).
+    pub fn generate(
+        settings: &MapGenSettings,
+        rng: &mut FloorRng,
+        depth: u32,
+        vaults: &[&Vault],
+        bestiary: &Bestiary,
+    ) -> MapGenerator {
+        let FloorRng { map: rng, monsters: monster_rng, items: item_rng } = rng;
+
+        let mut map = Map(Grid::new(settings.map_size));
+        let mut rooms: Vec<Rect> = Vec::with_capacity(50);
+
+        match settings.algorithm {
+            MapAlgorithm::Rooms => generate_rooms(&mut map, settings, rng, &mut rooms),
+            MapAlgorithm::Bsp => bsp::generate_rooms(&mut map, settings, rng, &mut rooms),
+            MapAlgorithm::Cave => cave::generate_caves(&mut map, settings, rng, &mut rooms),
+        }
+        if rooms.is_empty() {
+            panic!("Map generation failed: no rooms were created.");
+        }
+
+        let vault = vault::stamp_vault(&mut map, vaults, &rooms, depth, rng);
+        if let Some(vault) = &vault {
+            debug!("Stamped vault {} at {}", vault.name, vault.area);
+        }
+
+        let mut map = MapGenerator { map, rooms, vault, monsters: Vec::new(), items: Vec::new() };
+
+        let start = map.start_position();
+        let report = connectivity::repair_connectivity(&mut map.map, start);
+        if !report.is_connected() {
+            warn!("{} floor regions are still unreachable after repairs", report.disconnected.len());
+        }
+
+        // Cave sectors aren't walled in, so they don't get doors
+        if settings.algorithm != MapAlgorithm::Cave {
+            place_doors(&mut map.map, &map.rooms);
+        }
+
+        let start = map.start_position();
+        features::scatter_features(&mut map.map, &map.rooms, settings, depth, start, rng);
+
+        map.place_stairs(depth);
+
+        let mut placed: HashSet<IVec2> = HashSet::default();
+
+        map.place_monsters(settings, bestiary, monster_rng, &mut placed, depth);
+        map.place_vault_monsters(bestiary, monster_rng, &mut placed, depth);
+        map.place_items(settings, item_rng, &mut placed);
+
+        map
+    }
+
+    /// The tile the player starts on, in the first room.
+    pub fn start_position(&self) -> IVec2 {
+        let Some(room) = self.rooms.first() else {
+            panic!("Cannot place player: no rooms exist.");
+        };
+        // Cave sectors aren't solid floor, so fall back to any floor tile in the room
+        Some(room.center())
+            .filter(|p| self.map.0[*p].is_traversable())
+            .or_else(|| room.iter().find(|p| self.map.0[*p].is_traversable()))
+            .unwrap_or(room.center())
+    }
+
+    /// Puts the stairs down in the last room and, below the first floor,
+    /// the stairs up under the player's starting position.
+    pub fn place_stairs(&mut self, depth: u32) {
+        let start = self.start_position();
+
+        if depth > 0 {
+            self.map.0[start] = MapTile::StairsUp;
+        }
+
+        let Some(room) = self.rooms.last() else {
+            return;
+        };
+        let down = Some(room.center())
+            .filter(|p| *p != start && self.map.0[*p] == MapTile::Floor)
+            .or_else(|| room.iter().find(|p| *p != start && self.map.0[*p] == MapTile::Floor));
+
+        match down {
+            Some(p) => self.map.0[p] = MapTile::StairsDown,
+            None => warn!("No room for the stairs down at depth {}", depth),
+        }
+    }
+
+    pub fn place_player(&self, commands: &mut Commands, player: Entity) {
+        let p = self.start_position();
+        let mut entity = commands.entity(player);
+
+        // Set the player's position
+        entity.insert(Position::from(p));
+        
+        let size = self.map.0.size();
+        let dims = size.to_array();
+        let len = (size.x * size.y) as usize;
+        
+        commands.entity(player)
+            .insert(MapView ( Grid::new(dims) ))
+            .insert(MapMemory ( vec![false; len] ));
+        
+        println!("Setting player position to {}", p);
+        println!("Player FOV & Memory initialized to {}×{} ({} cells)", size.x, size.y, len);
+    }
+
+    pub fn place_monsters(
+        &mut self,
+        settings: &MapGenSettings,
+        bestiary: &Bestiary,
+        rng: &mut StdRng,
+        placed: &mut HashSet<IVec2>,
+        depth: u32,
+    ) {
+        // The first room is the player's room
+        for room in self.rooms.iter().skip(1) {
+            let count = rng.gen_range(settings.monsters_per_room.clone());
+
+            for _ in 0..=count {
+                for _ in 0..2 {
+                    // If the first try fails, try again
+                    let p = get_random_ivec(rng, room.min, room.max);
+
+                    if placed.contains(&p) || self.map.0[p] != MapTile::Floor {
+                        continue;
+                    }
+
+                    let Some(template) = bestiary.pick(rng, depth) else {
+                        return;
+                    };
+                    let mut monster = MonsterBundle::from_template(template);
+                    monster.scale_to_depth(depth);
+                    monster.movable.position = p.into();
+                    placed.insert(p);
+
+                    self.monsters.push(monster);
+
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl MapGenerator {
+    /// Adds the monsters marked in the stamped vault's template.
+    pub fn place_vault_monsters(
+        &mut self,
+        bestiary: &Bestiary,
+        rng: &mut StdRng,
+        placed: &mut HashSet<IVec2>,
+        depth: u32,
+    ) {
+        let Some(vault) = &self.vault else {
+            return;
+        };
+
+        for (p, glyph) in vault.monsters.iter() {
+            if !placed.insert(*p) {
+                continue;
+            }
+
+            let template = match glyph.and_then(|glyph| bestiary.by_glyph(glyph)) {
+                Some(template) => template,
+                None => {
+                    if let Some(glyph) = glyph {
+                        warn!("Vault {} wants unknown monster '{}', spawning a random one", vault.name, glyph);
+                    }
+                    let Some(template) = bestiary.pick(rng, depth) else {
+                        continue;
+                    };
+                    template
+                }
+            };
+            let mut monster = MonsterBundle::from_template(template);
+            monster.scale_to_depth(depth);
+            monster.movable.position = (*p).into();
+
+            self.monsters.push(monster);
+        }
+    }
+}
+
+impl MapGenerator {
+    /// Scatters items over the rooms and puts one on each of the vault's item spawns.
+    pub fn place_items(
+        &mut self,
+        settings: &MapGenSettings,
+        rng: &mut StdRng,
+        placed: &mut HashSet<IVec2>,
+    ) {
+        for room in self.rooms.iter() {
+            let count = rng.random_range(settings.items_per_room.clone());
+
+            for _ in 0..count {
+                let p = get_random_ivec(rng, room.min, room.max);
+
+                if self.map.0[p] != MapTile::Floor || !placed.insert(p) {
+                    continue;
+                }
+                self.items.push((p, ItemKind::random(rng)));
+            }
+        }
+
+        let Some(vault) = &self.vault else {
+            return;
+        };
+        for p in vault.items.iter() {
+            if placed.insert(*p) {
+                self.items.push((*p, ItemKind::random(rng)));
+            }
+        }
+    }
+}
+
+fn get_random_ivec(rng: &mut StdRng, min: IVec2, max: IVec2) -> IVec2 {
+    let p_x = rng.gen_range(min.x..max.x);
+    let p_y = rng.gen_range(min.y..max.y);
+
+    IVec2::new(p_x, p_y)
+}
+
+fn generate_rooms(
+    map: &mut Map,
+    settings: &MapGenSettings,
+    rng: &mut StdRng,
+    rooms: &mut Vec<Rect>,
+) {
+    // create a guaranteed starting room
+    let smallest_u = settings.room_size.start;
+    let smallest = smallest_u as i32;
+
+    let map_width  = map.0.width() as i32;
+    let map_height = map.0.height() as i32;
+    let center_x   = map_width  / 2;
+    let center_y   = map_height / 2;
+    
+    let first_room = Rect::from_position_size(
+        (center_x, center_y),
+        (smallest, smallest)
+    );
+    build_room(map, &first_room);
+    rooms.push(first_room);
+    
+    for _ in 0..settings.iterations {
+        let w = rng.random_range(settings.room_size.clone());
+        let h = rng.random_range(settings.room_size.clone());
+
+        let max_x = map.side_index(Side::Right).saturating_sub(w + 1);
+        let max_y = map.side_index(Side::Bottom).saturating_sub(h + 1);
+
+        if max_x <= 2 || max_y <= 2 {
+            // Skip this iteration if the room won't fit
+            continue;
+        }
+
+        let x = rng.random_range(2..=max_x);
+        let y = rng.random_range(2..=max_y);
+
+        let new_room = Rect::from_position_size((x as i32, y as i32), (w as i32, h as i32));
+
+        println!("Creating room {}", new_room);
+
+        let mut ok = true;
+
+        for room in rooms.iter() {
+            if new_room.overlaps(room) {
+                println!("New room overlaps {}!", room);
+                ok = false;
+                break;
+            }
+        }
+
+        if ok {
+            println!("Building new room!");
+            build_room(map, &new_room);
+
+            if !rooms.is_empty() {
+                let prev_room = &rooms[rooms.len() - 1];
+                build_tunnels_between_rooms(map, rng, prev_room, &new_room);
+            }
+
+            rooms.push(new_room);
+        }
+    }
+}
+
+fn build_room(map: &mut Map, room: &Rect) {
+    for pos in room.iter() {
+        map.0[pos] = MapTile::Floor;
+    }
+}
+
+fn build_tunnels_between_rooms(map: &mut Map, rng: &mut StdRng, room_a: &Rect, room_b: &Rect) {
+    let (new_x, new_y) = room_b.center().into();
+    let (prev_x, prev_y) = room_a.center().into();
+
+    if rng.gen_bool(0.5) {
+        build_horizontal_tunnel(map, prev_x, new_x, prev_y);
+        build_vertical_tunnel(map, prev_y, new_y, new_x);
+    } else {
+        build_vertical_tunnel(map, prev_y, new_y, prev_x);
+        build_horizontal_tunnel(map, prev_x, new_x, new_y);
+    }
+}
+
+fn build_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
+    let min = x1.min(x2);
+    let max = x1.max(x2);
+
+    for x in min..=max {
+        carve_tunnel(map, IVec2::new(x, y));
+    }
+}
+
+fn build_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
+    let min = y1.min(y2);
+    let max = y1.max(y2);
+
+    for y in min..=max {
+        carve_tunnel(map, IVec2::new(x, y));
+    }
+}
+
+/// Puts a closed door wherever a tunnel leaves a room through its wall.
+fn place_doors(map: &mut Map, rooms: &[Rect]) {
+    for room in rooms {
+        let horizontal = (room.min.x - 1..=room.max.x)
+            .flat_map(|x| [IVec2::new(x, room.min.y - 1), IVec2::new(x, room.max.y)]);
+        let vertical = (room.min.y..room.max.y)
+            .flat_map(|y| [IVec2::new(room.min.x - 1, y), IVec2::new(room.max.x, y)]);
+
+        for p in horizontal.chain(vertical) {
+            if is_doorway(map, p) {
+                map.0[p] = MapTile::DoorClosed;
+            }
+        }
+    }
+}
+
+/// A floor tile squeezed between walls on exactly one axis.
+fn is_doorway(map: &Map, p: IVec2) -> bool {
+    if !map.0.in_bounds(p) || map.0[p] != MapTile::Floor {
+        return false;
+    }
+
+    let is_wall = |dir: IVec2| {
+        let n = p + dir;
+        !map.0.in_bounds(n) || map.0[n] == MapTile::Wall
+    };
+
+    let walled_x = is_wall(IVec2::X) && is_wall(IVec2::NEG_X);
+    let walled_y = is_wall(IVec2::Y) && is_wall(IVec2::NEG_Y);
+
+    walled_x != walled_y
+}
+
+/// Turns a wall into floor, leaving the outer wall of the map intact.
+fn carve_tunnel(map: &mut Map, p: IVec2) {
+    let inner = p.cmpgt(IVec2::ZERO).all()
+        && p.x < map.0.width() as i32 - 1
+        && p.y < map.0.height() as i32 - 1;
+
+    if inner && map.0[p] == MapTile::Wall {
+        map.0[p] = MapTile::Floor;
+    }
+}
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct PathMap2d {
+    grid: Grid<bool>,
+}
+
+impl PathMap2d {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            grid: Grid::new([width, height]),
+        }
+    }
+
+    pub fn grid_mut(&mut self) -> &mut Grid<bool> {
+        &mut self.grid
+    }
+
+    pub fn grid(&self) -> &Grid<bool> {
+        &self.grid
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.grid.size()
+    }
+
+    pub fn clear(&mut self) {
+        for cell in self.grid.iter_mut() {
+            *cell = false;
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! Hand-made rooms stamped into generated floors.
+//!
+//! Vaults are authored as text files in `assets/vaults/`. A small header is
+//! followed by `---` and the ASCII layout:
+//!
+//! ```text
+//! name: Treasure Vault
+//! min_depth: 1
+//! ---
+//! #######
+//! #.$.$.#
+//! #..o..#
+//! #######
+//! ```
+//!
+//! | Char      | Meaning                                    |
+//! |-----------|--------------------------------------------|
+//! | ` `       | Leave the generated tile as it is          |
+//! | `#`       | Wall                                       |
+//! | `.`       | Floor                                      |
+//! | `$`       | Floor with an item spawn                   |
+//! | `M`       | Floor with a random monster                |
+//! | `a`..=`z` | Floor with the monster using that glyph    |
+
+use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+use bevy::prelude::*;
+use rand::{prelude::StdRng, Rng};
+use sark_grids::SizedGrid;
+use thiserror::Error;
+
+use crate::shapes::Rect;
+
+use super::{Map, MapTile};
+
+/// Folder the vault templates are loaded from, relative to the `assets` folder.
+pub const VAULT_FOLDER: &str = "vaults";
+
+/// Attempts at finding free space for a vault before giving up on it.
+const PLACEMENT_ATTEMPTS: u32 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VaultCell {
+    Keep,
+    Wall,
+    Floor,
+    Item,
+    /// A monster spawn, either a specific glyph or any monster.
+    Monster(Option<char>),
+}
+
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Vault {
+    pub name: String,
+    /// Shallowest floor the vault can show up on.
+    pub min_depth: u32,
+    /// Layout rows, top row first.
+    pub rows: Vec<Vec<VaultCell>>,
+}
+
+impl Vault {
+    pub fn width(&self) -> i32 {
+        self.rows.iter().map(|row| row.len()).max().unwrap_or(0) as i32
+    }
+
+    pub fn height(&self) -> i32 {
+        self.rows.len() as i32
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VaultParseError {
+    #[error("missing `---` between the header and the layout")]
+    MissingSeparator,
+    #[error("missing `name:` in the header")]
+    MissingName,
+    #[error("line {line}: unknown header key `{key}`")]
+    UnknownKey { line: usize, key: String },
+    #[error("line {line}: `{value}` is not a valid depth")]
+    InvalidDepth { line: usize, value: String },
+    #[error("line {line}, column {column}: unknown tile `{tile}`")]
+    UnknownTile { line: usize, column: usize, tile: char },
+    #[error("the layout is empty")]
+    EmptyLayout,
+}
+
+pub fn parse_vault(text: &str) -> Result<Vault, VaultParseError> {
+    let mut lines = text.lines().enumerate();
+    let mut name = None;
+    let mut min_depth = 0;
+    let mut found_separator = false;
+
+    for (i, line) in lines.by_ref() {
+        let line = line.trim();
+        if line == "---" {
+            found_separator = true;
+            break;
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let (key, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.trim();
+        match key.trim() {
+            "name" => name = Some(value.to_string()),
+            "min_depth" => {
+                min_depth = value.parse().map_err(|_| VaultParseError::InvalidDepth {
+                    line: i + 1,
+                    value: value.to_string(),
+                })?
+            }
+            key => {
+                return Err(VaultParseError::UnknownKey {
+                    line: i + 1,
+                    key: key.to_string(),
+                });
+            }
+        }
+    }
+
+    if !found_separator {
+        return Err(VaultParseError::MissingSeparator);
+    }
+    let name = name.ok_or(VaultParseError::MissingName)?;
+
+    let mut rows = Vec::new();
+    for (i, line) in lines {
+        let mut row = Vec::new();
+        for (column, c) in line.trim_end().chars().enumerate() {
+            row.push(match c {
+                ' ' => VaultCell::Keep,
+                '#' => VaultCell::Wall,
+                '.' => VaultCell::Floor,
+                '$' => VaultCell::Item,
+                'M' => VaultCell::Monster(None),
+                'a'..='z' => VaultCell::Monster(Some(c)),
+                tile => {
+                    return Err(VaultParseError::UnknownTile {
+                        line: i + 1,
+                        column: column + 1,
+                        tile,
+                    });
+                }
+            });
+        }
+        rows.push(row);
+    }
+
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+    if rows.is_empty() {
+        return Err(VaultParseError::EmptyLayout);
+    }
+
+    Ok(Vault {
+        name,
+        min_depth,
+        rows,
+    })
+}
+
+#[derive(Debug, Error)]
+pub enum VaultLoaderError {
+    #[error("Error reading {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Error parsing {path}: {source}")]
+    Parse {
+        path: String,
+        #[source]
+        source: VaultParseError,
+    },
+}
+
+/// Loads [Vault] templates from `.vault` files.
+#[derive(Default)]
+pub struct VaultLoader;
+
+impl AssetLoader for VaultLoader {
+    type Asset = Vault;
+    type Settings = ();
+    type Error = VaultLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let path = load_context.path().display().to_string();
+
+        let mut bytes = Vec::new();
+        if let Err(source) = reader.read_to_end(&mut bytes).await {
+            return Err(VaultLoaderError::Io { path, source });
+        }
+
+        parse_vault(&String::from_utf8_lossy(&bytes)).map_err(|source| VaultLoaderError::Parse { path, source })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["vault"]
+    }
+}
+
+/// Keeps the vault folder loaded.
+#[derive(Resource)]
+pub struct VaultFolderHandle(pub Handle<bevy::asset::LoadedFolder>);
+
+pub fn load_vaults(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(VaultFolderHandle(asset_server.load_folder(VAULT_FOLDER)));
+}
+
+/// A vault that has been stamped into a map.
+pub struct StampedVault {
+    pub name: String,
+    pub area: Rect,
+    pub monsters: Vec<(IVec2, Option<char>)>,
+    pub items: Vec<IVec2>,
+}
+
+/// Stamps one of the vaults allowed at `depth` into solid rock that doesn't
+/// overlap any of the generated `rooms`.
+pub fn stamp_vault(
+    map: &mut Map,
+    vaults: &[&Vault],
+    rooms: &[Rect],
+    depth: u32,
+    rng: &mut StdRng,
+) -> Option<StampedVault> {
+    let eligible: Vec<_> = vaults.iter().filter(|v| v.min_depth <= depth).collect();
+    if eligible.is_empty() {
+        return None;
+    }
+    let vault = eligible[rng.random_range(0..eligible.len())];
+
+    // Keep the outer wall of the map intact
+    let max_x = map.0.width() as i32 - vault.width() - 1;
+    let max_y = map.0.height() as i32 - vault.height() - 1;
+    if max_x < 1 || max_y < 1 {
+        return None;
+    }
+
+    for _ in 0..PLACEMENT_ATTEMPTS {
+        let pos = (rng.random_range(1..=max_x), rng.random_range(1..=max_y));
+        let area = Rect::from_position_size(pos, (vault.width(), vault.height()));
+
+        let free = !rooms.iter().any(|room| area.overlaps(room))
+            && area.iter().all(|p| map.0[p] == MapTile::Wall);
+
+        if free {
+            return Some(stamp_at(map, vault, area));
+        }
+    }
+
+    info!("No free space for vault {} at depth {}", vault.name, depth);
+    None
+}
+
+fn stamp_at(map: &mut Map, vault: &Vault, area: Rect) -> StampedVault {
+    let mut stamped = StampedVault {
+        name: vault.name.clone(),
+        area,
+        monsters: Vec::new(),
+        items: Vec::new(),
+    };
+
+    for (y, row) in vault.rows.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            // Layouts are written top row first, the map's y axis points up
+            let p = IVec2::new(area.min.x + x as i32, area.max.y - 1 - y as i32);
+
+            match cell {
+                VaultCell::Keep => continue,
+                VaultCell::Wall => map.0[p] = MapTile::Wall,
+                VaultCell::Floor => map.0[p] = MapTile::Floor,
+                VaultCell::Item => {
+                    map.0[p] = MapTile::Floor;
+                    stamped.items.push(p);
+                }
+                VaultCell::Monster(glyph) => {
+                    map.0[p] = MapTile::Floor;
+                    stamped.monsters.push((p, *glyph));
+                }
+            }
+        }
+    }
+
+    stamped
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{prelude::StdRng, SeedableRng};
+    use sark_grids::Grid;
+
+    use crate::map::{Map, MapTile};
+
+    use super::{parse_vault, stamp_vault, VaultCell, VaultParseError};
+
+    const SHRINE: &str = "name: Shrine\nmin_depth: 2\n---\n#####\n#.$.#\n#.o.#\n#####\n";
+
+    #[test]
+    fn parse() {
+        let vault = parse_vault(SHRINE).unwrap();
+
+        assert_eq!("Shrine", vault.name);
+        assert_eq!(2, vault.min_depth);
+        assert_eq!((5, 4), (vault.width(), vault.height()));
+        assert_eq!(VaultCell::Item, vault.rows[1][2]);
+        assert_eq!(VaultCell::Monster(Some('o')), vault.rows[2][2]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(VaultParseError::MissingSeparator), parse_vault("name: Shrine\nmin_depth: 1").map(|_| ()));
+        assert_eq!(Err(VaultParseError::MissingName), parse_vault("---\n###").map(|_| ()));
+        assert_eq!(
+            Err(VaultParseError::UnknownTile { line: 3, column: 2, tile: '?' }),
+            parse_vault("name: Shrine\n---\n#?#").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn stamp_respects_depth() {
+        let vault = parse_vault(SHRINE).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let mut map = Map(Grid::new([30, 20]));
+        assert!(stamp_vault(&mut map, &[&vault], &[], 1, &mut rng).is_none());
+
+        let stamped = stamp_vault(&mut map, &[&vault], &[], 2, &mut rng).unwrap();
+        assert_eq!(1, stamped.items.len());
+        assert_eq!(1, stamped.monsters.len());
+        assert_eq!(6, map.0.iter().filter(|t| **t == MapTile::Floor).count());
+    }
+}
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use sark_grids::Grid;
 use sark_pathfinding::*;
 
-use crate::{map::{Map, MapTile}, movement::Position, AppState};
+use crate::{map::Map, movement::Position, turn_system::turns_running};
 use crate::PathMap2dExt::PathMap2dExt;
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
@@ -11,7 +11,7 @@ pub struct MapStatePlugin;
 
 impl Plugin for MapStatePlugin {
     fn build(&self, app: &mut App) {
-        app.configure_sets(Update, UpdateMapStateSet.run_if(in_state(AppState::Lore)))
+        app.configure_sets(Update, UpdateMapStateSet.run_if(turns_running))
             .add_systems(Update, update_map_state_system.in_set(UpdateMapStateSet))
             .init_resource::<MapObstacles>()
             .init_resource::<MapActors>();
@@ -55,41 +55,50 @@ fn update_map_state_system(
     }
 
     if let Ok(map) = q_map.single() {
-        if UVec2::from_array(<[u32; 2]>::from(blockers.0.size())) != map.0.size() {
-            blockers.0 = PathMap2d::new(map.0.size().to_array());
-        }
+        let blockers_iter = q_blockers.iter().map(|(entity, pos)| (entity, pos.0));
+        rebuild_map_state(map, blockers_iter, &mut blockers, &mut entities);
+    }
+}
 
-        if entities.0.width() * entities.0.height() != map.0.tile_count() {
-            entities.0 = Grid::new(map.0.size());
-        }
+/// Recomputes the obstacle grid and the actor lookup from scratch.
+pub fn rebuild_map_state(
+    map: &Map,
+    positions: impl Iterator<Item = (Entity, IVec2)>,
+    blockers: &mut MapObstacles,
+    entities: &mut MapActors,
+) {
+    if UVec2::from_array(<[u32; 2]>::from(blockers.0.size())) != map.0.size() {
+        blockers.0 = PathMap2d::new(map.0.size().to_array());
+    }
+
+    if entities.0.width() * entities.0.height() != map.0.tile_count() {
+        entities.0 = Grid::new(map.0.size());
+    }
 
-        let grid = blockers.0.grid_mut();
-        let grid_len = grid.width() * grid.height();
+    let grid = blockers.0.grid_mut();
+    let grid_len = grid.width() * grid.height();
 
-        for (i, tile) in map.0.iter().enumerate() {
-            if i < grid_len {
-                grid[i] = *tile == MapTile::Wall;
-            }
+    for (i, tile) in map.0.iter().enumerate() {
+        if i < grid_len {
+            grid[i] = tile.properties().blocks_movement;
         }
+    }
 
 
-        for entry in entities.0.iter_mut() {
-            *entry = None;
-        }
+    for entry in entities.0.iter_mut() {
+        *entry = None;
+    }
 
-        let entities_len = entities.0.width() * entities.0.height();
-
-        for (entity, pos) in q_blockers.iter() {
-            let i = map.0.transform_lti(pos.0);
-            if i < grid_len && i < entities_len {
-                blockers.0.grid_mut()[i] = true;
-                entities.0[i] = Some(entity);
-            } else {
-                // Optionally log or warn about the out-of-bounds index
-                warn!("Entity position {:?} out of bounds for map size {:?}", pos.0, map.0.size());
-            }
+    let entities_len = entities.0.width() * entities.0.height();
+
+    for (entity, pos) in positions {
+        let i = map.0.transform_lti(pos);
+        if i < grid_len && i < entities_len {
+            blockers.0.grid_mut()[i] = true;
+            entities.0[i] = Some(entity);
+        } else {
+            // Optionally log or warn about the out-of-bounds index
+            warn!("Entity position {:?} out of bounds for map size {:?}", pos, map.0.size());
         }
     }
 }
-
-
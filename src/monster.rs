@@ -1,45 +1,52 @@
 use bevy::prelude::*;
 use bracket_random::prelude::{DiceType};
-use sark_grids::Grid;
-use sark_pathfinding::*;
-use sark_pathfinding::PathMap2d;
+use sark_grids::{Grid, SizedGrid};
 use controlled_astar::{AStar, node::{Node, Direction}};
-use bevy_ascii_terminal::color;
 
 use astar;
 
-use crate::{bundle::MovingEntityBundle, map_state::{
+use crate::{action::{Action, ActionEvent, ResolveActionsSet}, bundle::MovingEntityBundle, map_state::{
     PathBlocker,
     MapObstacles,
-    MapActors
 }, visibility::{
     MapView,
     VIEW_SYSTEM_LABEL,
     ViewRange
-}, turn_system::{
-    Energy,
-    TakingATurn
-}, combat::{
+}, turn_system::TakingATurn, combat::{
     CombatantBundle,
     HitPoints,
     MaxHitPoints,
     Defense, Strength,
-    TargetEvent,
-    ActorEffect, AttackDice
-}, movement::Position, player::Player, rng::DiceRng, AppState};
+    AttackDice
+}, bestiary::{self, Bestiary, BestiaryLoader, MonsterTemplate}, experience::XpReward, loot::LootTable, map::Map, movement::Position, player::Player, replay::playing_back, GamePhase};
+use crate::PathMap2dExt::PathMap2dExt;
 use crate::visibility::ViewSystemSet;
 
 pub struct MonstersPlugin;
 
 impl Plugin for MonstersPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, monster_ai.after(ViewSystemSet).run_if(in_state(AppState::WeaponSetup)));
+        app.init_asset::<Bestiary>()
+            .init_asset_loader::<BestiaryLoader>()
+            .add_systems(Startup, bestiary::load_bestiary)
+            .add_systems(Update, bestiary::log_bestiary_errors)
+            .add_systems(Update, monster_ai.after(ViewSystemSet).before(ResolveActionsSet).run_if(in_state(GamePhase::MonsterTurn).and(not(playing_back))));
     }
 }
 
 #[derive(Component, Default)]
 pub struct Monster;
 
+/// Whether a monster can open closed doors on its way to the player.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct OpensDoors(pub bool);
+
+impl Default for OpensDoors {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
 #[derive(Bundle)]
 pub struct MonsterBundle {
     #[bundle()]
@@ -51,82 +58,87 @@ pub struct MonsterBundle {
     pub blocker: PathBlocker,
     pub vision: MapView,
     pub view_range: ViewRange,
+    pub opens_doors: OpensDoors,
+    pub xp: XpReward,
+    pub loot: LootTable,
 }
 
 impl MonsterBundle {
-    pub fn new_goblin() -> Self {
-        MonsterBundle {
-            movable: MovingEntityBundle::new(Color::from(color::RED), 'g', 20),
-            combatant_bundle: CombatantBundle {
-                hp: HitPoints(15),
-                max_hp: MaxHitPoints(15),
-                defense: Defense(0),
-                strength: Strength(1),
-                attack_dice: AttackDice(DiceType::new(1,4,0)),
-            },
-            monster: Default::default(),
-            name: Name::new("Goblin"),
-            blocker: Default::default(),
-            vision: Default::default(),
-            view_range: ViewRange(4),
-        }
-    }
+    pub fn from_template(template: &MonsterTemplate) -> Self {
+        let (count, sides, bonus) = template.dice;
 
-    pub fn new_orc() -> Self {
         Self {
-            movable: MovingEntityBundle::new(Color::from(color::RED), 'o', 15),
+            movable: MovingEntityBundle::new(template.color.color(), template.glyph, template.speed),
             combatant_bundle: CombatantBundle {
-                hp: HitPoints(25),
-                max_hp: MaxHitPoints(25),
-                defense: Defense(1),
-                strength: Strength(3),
-                attack_dice: AttackDice(DiceType::new(2,6,0)),
+                hp: HitPoints(template.hp),
+                max_hp: MaxHitPoints(template.hp),
+                defense: Defense(template.defense),
+                strength: Strength(template.strength),
+                attack_dice: AttackDice(DiceType::new(count, sides, bonus)),
+                status: Default::default(),
             },
             monster: Default::default(),
-            name: Name::new("Orc"),
+            name: Name::new(template.name.clone()),
             blocker: Default::default(),
             vision: Default::default(),
-            view_range: ViewRange(4),
-        }
-    }
-
-    pub fn get_from_index(index: u32) -> MonsterBundle {
-        match index {
-            0 => MonsterBundle::new_goblin(),
-            1 => MonsterBundle::new_orc(),
-            _ => MonsterBundle::new_goblin(),
+            view_range: ViewRange(template.view_range),
+            opens_doors: OpensDoors(template.opens_doors),
+            xp: XpReward(template.xp),
+            loot: template.loot.clone(),
         }
     }
 
-    pub fn max_index() -> u32 {
-        2
+    /// Makes the monster tougher for every floor below the first.
+    pub fn scale_to_depth(&mut self, depth: u32) {
+        let depth = depth as i32;
+        let combatant = &mut self.combatant_bundle;
+
+        let hp = combatant.max_hp.0 + combatant.max_hp.0 * depth / 5;
+        combatant.hp.0 = hp;
+        combatant.max_hp.0 = hp;
+        combatant.strength.0 += depth / 2;
+        combatant.defense.0 += depth / 3;
+        self.xp.0 += self.xp.0 * depth / 4;
+        self.loot.scale_to_depth(depth as u32);
     }
 }
 
 fn monster_ai(
-    mut obstacles: ResMut<MapObstacles>,
-    mut entities: ResMut<MapActors>,
+    obstacles: Res<MapObstacles>,
     q_player: Query<(Entity, &Position), With<Player>>,
-    mut q_monster: Query<(Entity, &mut Position, &mut Energy, &AttackDice, &MapView, &Name), (With<Monster>, Without<Player>, With<TakingATurn>)>,
-    mut attack_events: EventWriter<TargetEvent>,
-    mut rng: Local<DiceRng>,
+    q_monster: Query<(Entity, &Position, &MapView, &OpensDoors), (With<Monster>, Without<Player>, With<TakingATurn>)>,
+    q_map: Query<&Map>,
+    mut evt_action: EventWriter<ActionEvent>,
 ) {
-    for (entity, mut pos, mut energy, dice, view, _name) in q_monster.iter_mut() {
-        let pos = &mut pos.0;
+    let Ok(map) = q_map.single() else {
+        return;
+    };
+
+    for (entity, pos, view, opens_doors) in q_monster.iter() {
+        let pos = pos.0;
+        let mut action = Action::Wait;
 
-        if let Ok((player, player_pos)) = q_player.get_single() {
+        if let Ok((player, player_pos)) = q_player.single() {
             let player_pos = player_pos.0;
 
             if view.0[player_pos] {
-                {
-                    // Access the grid inside PathMap2d in a separate scope
-                    let grid = get_pathmap_grid_mut(&mut obstacles.0);
-                    grid[*pos] = false;    // Remove the obstacle at monster's position
-                    grid[player_pos] = false;  // Remove the obstacle at player's position
-                }
                 // Convert the grid to a HashMap of Nodes for AStar
-                let grid = get_pathmap_grid_mut(&mut obstacles.0);
-                let grid_vec = grid_bool_to_vec_vec_i32(grid);
+                let mut grid_vec = grid_bool_to_vec_vec_i32(obstacles.0.grid());
+                for (i, tile) in map.0.iter().enumerate() {
+                    let p = map.0.transform_itl(i);
+                    let props = tile.properties();
+                    if props.damage > 0 {
+                        // Monsters know better than to walk into lava
+                        grid_vec[p.y as usize][p.x as usize] = 1;
+                    } else if opens_doors.0 && props.opens_into.is_some() {
+                        // Closed doors only cost a turn to open
+                        grid_vec[p.y as usize][p.x as usize] = 0;
+                    }
+                }
+                // The monster and the player block themselves on the obstacle grid
+                grid_vec[pos.y as usize][pos.x as usize] = 0;
+                grid_vec[player_pos.y as usize][player_pos.x as usize] = 0;
+
                 let nodes = Node::grid_to_nodes(&grid_vec);
                 
                 let mut astar = AStar::new(nodes);
@@ -135,29 +147,19 @@ fn monster_ai(
                 let goal = (player_pos.x as usize, player_pos.y as usize);
                 if let Ok(Some(path)) = astar.find_shortest_path(start, goal) {
                     if path.len() == 2 {
-                        let damage = rng.roll(dice.0);
-                        attack_events.send(TargetEvent {
-                            actor: entity,
-                            target: player,
-                            effect: ActorEffect::Damage(damage),
-                        });
-                    } else if path.len() > 1{
-                        entities.0[*pos] = None;
-                        *pos = IVec2::new(path[1].0 as i32, path[1].1 as i32);
-                        entities.0[*pos] = Some(entity);
+                        action = Action::Attack(player);
+                    } else if path.len() > 1 {
+                        let next = IVec2::new(path[1].0 as i32, path[1].1 as i32);
+                        action = Action::Move(next - pos);
                     }
                 }
-
-                {
-                    // Access the grid again to restore obstacles
-                    let grid = get_pathmap_grid_mut(&mut obstacles.0);
-                    grid[*pos] = true;    // Restore the obstacle at monster's position
-                    grid[player_pos] = true;  // Restore the obstacle at player's position
-                }
             }
         }
 
-        energy.0 = 0;
+        evt_action.write(ActionEvent {
+            actor: entity,
+            action,
+        });
     }
 }
 
@@ -171,10 +173,3 @@ fn grid_bool_to_vec_vec_i32(grid: &Grid<bool>) -> Vec<Vec<i32>> {
     }
     result
 }
-
-fn get_pathmap_grid_mut(map: &mut PathMap2d) -> &mut Grid<bool> {
-    unsafe {
-        // SAFELY cast PathMap2d to a Grid<bool> because PathMap2d wraps Grid<bool>
-        &mut *(map as *mut PathMap2d as *mut Grid<bool>)
-    }
-}
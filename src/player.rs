@@ -1,10 +1,7 @@
 use bevy::prelude::*;
 
 use bevy::input::keyboard::{KeyCode};
-use bracket_random::prelude::DiceType;
-use sark_grids::{Grid, SizedGrid};
-use sark_pathfinding::PathMap2d;
-use crate::{bundle::MovingEntityBundle, map_state::{MapActors, MapObstacles}, monster::Monster, movement::{Movement, Position}, visibility::{MapMemory, MapView, ViewRange}, events::AttackEvent, turn_system::{TakingATurn, Energy}, combat::{CombatantBundle, HitPoints, MaxHitPoints, Defense, Strength, TargetEvent, ActorEffect, AttackDice}, rng::DiceRng, AppState};
+use crate::{action::{Action, ActionEvent, ResolveActionsSet}, bundle::MovingEntityBundle, dungeon::FloorChange, equipment::{derived_stats, BaseStats, Equipment}, experience::Experience, item::Inventory, loot::Purse, map_state::PathBlocker, visibility::{MapMemory, MapView, ViewRange}, turn_system::TakingATurn, combat::{CombatantBundle, HitPoints, MaxHitPoints}, replay::playing_back, AppState, GamePhase};
 
 pub struct PlayerPlugin;
 
@@ -16,7 +13,7 @@ impl Plugin for PlayerPlugin {
         app
             .init_resource::<PlayerSpawned>()
             .add_systems(OnEnter(AppState::Lore), spawn_player.in_set(PlayerSpawnSet))
-            .add_systems(Update, player_input.run_if(in_state(AppState::InGame)));
+            .add_systems(Update, player_input.before(ResolveActionsSet).run_if(in_state(GamePhase::PlayerTurn).and(not(playing_back))));
 
     }
 }
@@ -40,6 +37,9 @@ fn spawn_player(
 #[derive(Component, Default, Debug)]
 pub struct Player;
 
+/// The player's speed before equipment.
+pub const PLAYER_SPEED: i32 = 25;
+
 #[derive(Debug, Bundle)]
 pub struct PlayerBundle {
     #[bundle()]
@@ -51,92 +51,75 @@ pub struct PlayerBundle {
     pub name: Name,
     pub memory: MapMemory,
     pub view_range: ViewRange,
+    pub blocker: PathBlocker,
+    pub experience: Experience,
+    pub inventory: Inventory,
+    pub base_stats: BaseStats,
+    pub equipment: Equipment,
+    pub purse: Purse,
 }
 
 impl Default for PlayerBundle {
     fn default() -> Self {
+        let base_stats = BaseStats::default();
+        let equipment = Equipment::default();
+        let (defense, strength, attack_dice, speed) = derived_stats(&base_stats, &equipment);
+
+        let mut move_bundle = MovingEntityBundle::new(Color::WHITE, '@', PLAYER_SPEED);
+        move_bundle.speed = speed;
+
         Self {
-            move_bundle: MovingEntityBundle::new(Color::WHITE, '@', 25),
+            move_bundle,
             combatant_bundle: CombatantBundle {
                 hp: HitPoints(60),
                 max_hp: MaxHitPoints(60),
-                defense: Defense(1),
-                strength: Strength(3),
-                attack_dice: AttackDice(DiceType::new(5,3,0)),
+                defense,
+                strength,
+                attack_dice,
+                status: Default::default(),
             },
             player: Default::default(),
             view: Default::default(),
             name: Name::new("Player"),
             memory: Default::default(),
             view_range: ViewRange(5),
+            blocker: PathBlocker,
+            experience: Default::default(),
+            inventory: Default::default(),
+            base_stats,
+            equipment,
+            purse: Default::default(),
 
         }
     }
 }
-fn is_in_bounds(grid: &Grid<bool>, pos: IVec2) -> bool {
-    pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < grid.width() && (pos.y as usize) < grid.height()
-}
 fn player_input(
-    mut q_player: Query<(Entity, &Strength, &mut Position, &mut Energy, &AttackDice, &mut Movement), (With<Player>, With<TakingATurn>)>,
-    q_monsters: Query<&Name, With<Monster>>,
+    q_player: Query<Entity, (With<Player>, With<TakingATurn>)>,
     input: Res<ButtonInput<KeyCode>>,
-    mut obstacles: ResMut<MapObstacles>,
-    mut actors: ResMut<MapActors>,
-    _event_attack: EventWriter<AttackEvent>,
-    mut evt_attack: EventWriter<TargetEvent>,
-    mut rng: Local<DiceRng>,
+    mut evt_action: EventWriter<ActionEvent>,
 ) {
-    if let Ok((entity, _attack, mut pos, mut energy, dice, mut movement)) = q_player.single_mut() {
-        if read_wait(&input) {
-            energy.0 = 0;
-            return;
-        }
-
+    let Ok(entity) = q_player.single() else {
+        return;
+    };
+
+    let action = if read_wait(&input) {
+        Action::Wait
+    } else if let Some(change) = read_stairs(&input) {
+        Action::UseStairs(change)
+    } else if input.just_pressed(KeyCode::KeyG) {
+        Action::PickUp
+    } else {
         let move_input = read_movement(&input);
         if move_input.cmpeq(IVec2::ZERO).all() {
             return;
         }
+        Action::Move(move_input)
+    };
 
-        let curr = IVec2::from(pos.0);
-        let next = curr + move_input;
-        let attack = rng.roll(dice.0);
-
-        // Access the grid inside PathMap2d
-        let grid = get_pathmap_grid_mut(&mut obstacles.0);
-        if !is_in_bounds(grid, next) {
-            return;
-        }
-
-        if grid[next] {
-            if let Some(target) = actors.0[next] {
-                if let Ok(_name) = q_monsters.get(target) {
-                    evt_attack.send(TargetEvent {
-                        actor: entity,
-                        target,
-                        effect: ActorEffect::Damage(attack),
-                    });
-
-                    energy.0 = 0;
-                }
-            }
-            return;
-        }
-
-        pos.0 = next.into();
-        energy.0 = 0;
-        actors.0[curr] = None;
-        actors.0[next] = Some(entity);
-        grid[curr] = false;
-        grid[next] = true;
-        movement.0 = move_input.into();
-    }
-}
-
-fn get_pathmap_grid_mut(map: &mut PathMap2d) -> &mut Grid<bool> {
-    unsafe {
-        // SAFELY cast PathMap2d to a Grid<bool> because PathMap2d wraps Grid<bool>
-        &mut *(map as *mut PathMap2d as *mut Grid<bool>)
-    }
+    evt_action.write(ActionEvent {
+        actor: entity,
+        action,
+    });
 }
 
 fn read_movement(input: &ButtonInput<KeyCode>) -> IVec2 {
@@ -175,4 +158,14 @@ fn read_movement(input: &ButtonInput<KeyCode>) -> IVec2 {
 
 fn read_wait(input: &ButtonInput<KeyCode>) -> bool {
     input.just_pressed(KeyCode::Numpad5) || input.just_pressed(KeyCode::ControlLeft) || input.just_pressed(KeyCode::ControlRight)
-}
\ No newline at end of file
+}
+
+fn read_stairs(input: &ButtonInput<KeyCode>) -> Option<FloorChange> {
+    if input.just_pressed(KeyCode::Period) {
+        Some(FloorChange::Down)
+    } else if input.just_pressed(KeyCode::Comma) {
+        Some(FloorChange::Up)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,310 @@
+//! Practice mode, where turns can be taken back.
+//!
+//! At the start of every player turn the parts of the world that matter for
+//! play are copied into a [TurnSnapshot]. Pressing Backspace steps back one
+//! turn, and dying rewinds to the start of the turn instead of ending the run.
+//! Snapshots don't reach past the stairs. Practice runs aren't recorded, the
+//! dice rolled for an undone turn would leave a replay out of step.
+
+use std::collections::VecDeque;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    action::ResolveActionsSet,
+    bestiary::BestiaryAssets,
+    combat::{DeathSystemSet, HitPoints, MaxHitPoints, ResolveTargetEventsSet},
+    dungeon::{spawn_stored_monster, DungeonLevel, StoredFloor, StoredMonster},
+    equipment::{BaseStats, Equipment},
+    experience::Experience,
+    item::{Inventory, Item, ItemBundle, ItemKind},
+    loot::Purse,
+    map::Map,
+    map_state::{rebuild_map_state, MapActors, MapObstacles},
+    monster::Monster,
+    movement::Position,
+    player::Player,
+    replay::playing_back,
+    run_summary::RunStats,
+    status::StatusEffects,
+    turn_system::{Energy, TakingATurn, TurnBeginSet, TurnQueue},
+    ui::PrintLog,
+    visibility::MapMemory,
+    AppState, GamePhase,
+};
+
+/// How many turns can be taken back.
+pub const UNDO_DEPTH: usize = 20;
+
+pub struct PracticePlugin;
+
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PracticeMode>()
+            .init_resource::<TurnHistory>()
+            .add_systems(OnEnter(AppState::InGame), clear_history)
+            .add_systems(
+                Update,
+                (
+                    snapshot_system.after(TurnBeginSet),
+                    undo_system
+                        .after(snapshot_system)
+                        .run_if(in_state(GamePhase::PlayerTurn)),
+                )
+                    .before(ResolveActionsSet)
+                    .run_if(in_state(AppState::InGame).and(practicing).and(not(playing_back))),
+            )
+            .add_systems(
+                PostUpdate,
+                rewind_on_death
+                    .after(ResolveTargetEventsSet)
+                    .before(DeathSystemSet)
+                    .run_if(in_state(AppState::InGame).and(practicing).and(not(playing_back))),
+            );
+    }
+}
+
+/// Toggled from the main menu.
+#[derive(Resource, Default)]
+pub struct PracticeMode(pub bool);
+
+pub fn practicing(mode: Res<PracticeMode>) -> bool {
+    mode.0
+}
+
+/// The world as it was at the start of a player turn.
+pub struct TurnSnapshot {
+    depth: u32,
+    floor: StoredFloor,
+    player_position: IVec2,
+    player_hp: i32,
+    player_max_hp: i32,
+    player_energy: i32,
+    player_experience: Experience,
+    player_base_stats: BaseStats,
+    player_status: StatusEffects,
+    player_gold: i32,
+    player_inventory: Vec<ItemKind>,
+    player_equipment: Equipment,
+    stats: RunStats,
+}
+
+/// Snapshots of the last [UNDO_DEPTH] player turns, newest last.
+#[derive(Resource, Default)]
+pub struct TurnHistory(VecDeque<TurnSnapshot>);
+
+fn clear_history(mut history: ResMut<TurnHistory>) {
+    history.0.clear();
+}
+
+fn snapshot_system(
+    mut history: ResMut<TurnHistory>,
+    level: Res<DungeonLevel>,
+    stats: Res<RunStats>,
+    q_player: Query<
+        (
+            &Position,
+            &HitPoints,
+            &MaxHitPoints,
+            &Energy,
+            &MapMemory,
+            &Inventory,
+            &Equipment,
+            &Experience,
+            &BaseStats,
+            &StatusEffects,
+            &Purse,
+        ),
+        (With<Player>, Added<TakingATurn>),
+    >,
+    q_monsters: Query<(&Name, &Position, &HitPoints, &Energy, &StatusEffects), With<Monster>>,
+    q_items: Query<(&Item, &Position)>,
+    q_map: Query<&Map>,
+) {
+    if level.is_changed() {
+        history.0.clear();
+    }
+
+    let (Ok((pos, hp, max_hp, energy, memory, inventory, equipment, experience, base, status, purse)), Ok(map)) =
+        (q_player.single(), q_map.single())
+    else {
+        return;
+    };
+
+    let monsters = q_monsters
+        .iter()
+        .map(|(name, pos, hp, energy, status)| StoredMonster {
+            name: name.to_string(),
+            position: pos.0,
+            hp: hp.0,
+            energy: energy.0,
+            status: status.clone(),
+        })
+        .collect();
+
+    history.0.push_back(TurnSnapshot {
+        depth: level.depth,
+        floor: StoredFloor {
+            map: map.0.clone(),
+            memory: memory.0.clone(),
+            monsters,
+            items: q_items.iter().map(|(item, pos)| (pos.0, item.0)).collect(),
+        },
+        player_position: pos.0,
+        player_hp: hp.0,
+        player_max_hp: max_hp.0,
+        player_energy: energy.0,
+        player_experience: experience.clone(),
+        player_base_stats: base.clone(),
+        player_status: status.clone(),
+        player_gold: purse.0,
+        player_inventory: inventory.0.clone(),
+        player_equipment: equipment.clone(),
+        stats: stats.clone(),
+    });
+
+    if history.0.len() > UNDO_DEPTH {
+        history.0.pop_front();
+    }
+}
+
+/// Puts the world back the way a [TurnSnapshot] saw it.
+#[derive(SystemParam)]
+struct Rewind<'w, 's> {
+    commands: Commands<'w, 's>,
+    q_player: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut Position,
+            &'static mut HitPoints,
+            &'static mut MaxHitPoints,
+            &'static mut Energy,
+            &'static mut MapMemory,
+            &'static mut Inventory,
+            &'static mut Equipment,
+            &'static mut Experience,
+            &'static mut BaseStats,
+            &'static mut StatusEffects,
+            &'static mut Purse,
+            Has<TakingATurn>,
+        ),
+        With<Player>,
+    >,
+    q_monsters: Query<'w, 's, Entity, With<Monster>>,
+    q_items: Query<'w, 's, Entity, With<Item>>,
+    q_map: Query<'w, 's, &'static mut Map>,
+    obstacles: ResMut<'w, MapObstacles>,
+    actors: ResMut<'w, MapActors>,
+    queue: ResMut<'w, TurnQueue>,
+    stats: ResMut<'w, RunStats>,
+    log: ResMut<'w, PrintLog>,
+    bestiary: BestiaryAssets<'w>,
+}
+
+impl Rewind<'_, '_> {
+    fn restore(&mut self, snapshot: &TurnSnapshot) {
+        let Ok((
+            player,
+            mut pos,
+            mut hp,
+            mut max_hp,
+            mut energy,
+            mut memory,
+            mut inventory,
+            mut equipment,
+            mut experience,
+            mut base,
+            mut status,
+            mut purse,
+            has_turn,
+        )) = self.q_player.single_mut()
+        else {
+            return;
+        };
+        let Ok(mut map) = self.q_map.single_mut() else {
+            return;
+        };
+
+        for entity in self.q_monsters.iter().chain(self.q_items.iter()) {
+            self.commands.entity(entity).despawn();
+        }
+
+        map.0 = snapshot.floor.map.clone();
+        memory.0 = snapshot.floor.memory.clone();
+        pos.0 = snapshot.player_position;
+        hp.0 = snapshot.player_hp;
+        max_hp.0 = snapshot.player_max_hp;
+        energy.0 = snapshot.player_energy;
+        // Experience from undone kills and the levels it bought go too
+        *experience = snapshot.player_experience.clone();
+        *base = snapshot.player_base_stats.clone();
+        *status = snapshot.player_status.clone();
+        purse.0 = snapshot.player_gold;
+        inventory.0 = snapshot.player_inventory.clone();
+        *equipment = snapshot.player_equipment.clone();
+        // Undone turns, kills and hits don't count towards the summary
+        *self.stats = snapshot.stats.clone();
+
+        // It's the player's turn again, whoever was acting
+        if !has_turn {
+            self.queue.remove(player);
+            self.commands.entity(player).insert(TakingATurn);
+        }
+
+        let bestiary = self.bestiary.get();
+        let mut positions = vec![(player, pos.0)];
+        for stored in snapshot.floor.monsters.iter() {
+            if let Some(entity) = spawn_stored_monster(&mut self.commands, stored, &bestiary, snapshot.depth) {
+                positions.push((entity, stored.position));
+            }
+        }
+
+        for (p, kind) in snapshot.floor.items.iter() {
+            self.commands.spawn(ItemBundle::new(*kind, *p));
+        }
+
+        rebuild_map_state(&map, positions.into_iter(), &mut self.obstacles, &mut self.actors);
+    }
+}
+
+fn undo_system(
+    input: Res<ButtonInput<KeyCode>>,
+    mut history: ResMut<TurnHistory>,
+    mut rewind: Rewind,
+    q_turn: Query<(), (With<Player>, With<TakingATurn>)>,
+) {
+    if !input.just_pressed(KeyCode::Backspace) || q_turn.is_empty() {
+        return;
+    }
+
+    // The newest snapshot is the turn being taken right now
+    if history.0.len() < 2 {
+        rewind.log.push("Nothing to undo.".to_string());
+        return;
+    }
+    history.0.pop_back();
+
+    if let Some(snapshot) = history.0.back() {
+        rewind.restore(snapshot);
+        rewind.log.push("You step back in time.".to_string());
+    }
+}
+
+fn rewind_on_death(
+    mut history: ResMut<TurnHistory>,
+    mut rewind: Rewind,
+    q_player: Query<&HitPoints, With<Player>>,
+) {
+    if !q_player.single().is_ok_and(|hp| hp.0 <= 0) {
+        return;
+    }
+
+    // Taken again as soon as the turn starts over
+    let Some(snapshot) = history.0.pop_back() else {
+        return;
+    };
+    rewind.restore(&snapshot);
+    rewind.log.push("You would have died. The turn starts over.".to_string());
+}
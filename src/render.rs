@@ -4,11 +4,16 @@ use bevy_ascii_terminal::color::*;
 use bevy::prelude::*;
 use bevy_ascii_terminal::{terminal::Terminal, border::TerminalBorder, color, string::DecoratedString, StringDecorator, TerminalPlugins, Tile};
 use sark_grids::SizedGrid;
-use crate::{map::{Map, MapTile}, movement::Position, player::Player, visibility::{MapMemory, MapView}, GlobalTerminal, combat::ActorKilledEvent, AppState};
+use crate::{item::Item, map::{Map, MapTile}, movement::Position, player::Player, visibility::{MapMemory, MapView}, GlobalTerminal, combat::ActorKilledEvent, AppState};
 use crate::map::MapGenSetupSet;
 
 pub const WALL_COLOR: Color = Color::srgb(0.866, 0.866, 0.882);
 pub const FLOOR_COLOR: Color = Color::srgb(0.602, 0.462, 0.325);
+pub const STAIRS_COLOR: Color = Color::srgb(0.941, 0.827, 0.392);
+pub const DOOR_COLOR: Color = Color::srgb(0.545, 0.353, 0.169);
+pub const WATER_COLOR: Color = Color::srgb(0.247, 0.510, 0.898);
+pub const LAVA_COLOR: Color = Color::srgb(0.957, 0.373, 0.122);
+pub const RUBBLE_COLOR: Color = Color::srgb(0.533, 0.533, 0.533);
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct RenderSystemSet;
@@ -30,7 +35,8 @@ impl Plugin for RenderPlugin {
 
 fn first_frame_render(
     q_map: Query<&Map>,
-    q_entities: Query<(&Renderable, &Position)>,
+    q_items: Query<(&Renderable, &Position), With<Item>>,
+    q_entities: Query<(&Renderable, &Position), Without<Item>>,
     q_player: Query<(Entity, &MapView), With<Player>>,
     q_memory: Query<&MapMemory>,
     mut q_term: Query<&mut Terminal, With<GlobalTerminal>>,
@@ -45,9 +51,9 @@ fn first_frame_render(
             if let Ok(memory) = q_memory.get(entity) {
                 render_memory(memory, map, &mut term);
             }
-            render_view(player_view, &mut term, map, q_entities.iter());
+            render_view(player_view, &mut term, map, q_items.iter().chain(q_entities.iter()));
         } else {
-            render_everything(map, &mut term, q_entities.iter());
+            render_everything(map, &mut term, q_items.iter().chain(q_entities.iter()));
         }
     }
 }
@@ -69,7 +75,8 @@ fn index_to_pos(index: usize, width: u32) -> IVec2 {
 
 fn render(
     q_map: Query<&Map>,
-    q_entities: Query<(&Renderable, &Position)>,
+    q_items: Query<(&Renderable, &Position), With<Item>>,
+    q_entities: Query<(&Renderable, &Position), Without<Item>>,
     q_player: Query<(Entity, &MapView), With<Player>>,
     q_memory: Query<&MapMemory>,
     mut q_render_terminal: Query<&mut Terminal, With<GlobalTerminal>>,
@@ -90,13 +97,14 @@ fn render(
 
     term.clear();
 
+    // Items go first so whoever stands on one is drawn over it
     if let Ok((entity, player_view)) = q_player.single() {
         if let Ok(memory) = q_memory.get(entity) {
             render_memory(memory, map, &mut term);
         }
-        render_view(player_view, &mut term, map, q_entities.iter());
+        render_view(player_view, &mut term, map, q_items.iter().chain(q_entities.iter()));
     } else {
-        render_everything(map, &mut term, q_entities.iter());
+        render_everything(map, &mut term, q_items.iter().chain(q_entities.iter()));
     }
 }
 
@@ -113,6 +121,41 @@ impl From<MapTile> for Tile {
                 fg_color: LinearRgba::from(FLOOR_COLOR),
                 bg_color: LinearRgba::from(Color::BLACK),
             },
+            MapTile::StairsDown => Tile {
+                glyph: '>',
+                fg_color: LinearRgba::from(STAIRS_COLOR),
+                bg_color: LinearRgba::from(Color::BLACK),
+            },
+            MapTile::StairsUp => Tile {
+                glyph: '<',
+                fg_color: LinearRgba::from(STAIRS_COLOR),
+                bg_color: LinearRgba::from(Color::BLACK),
+            },
+            MapTile::DoorClosed => Tile {
+                glyph: '+',
+                fg_color: LinearRgba::from(DOOR_COLOR),
+                bg_color: LinearRgba::from(Color::BLACK),
+            },
+            MapTile::DoorOpen => Tile {
+                glyph: '\'',
+                fg_color: LinearRgba::from(DOOR_COLOR),
+                bg_color: LinearRgba::from(Color::BLACK),
+            },
+            MapTile::ShallowWater => Tile {
+                glyph: '~',
+                fg_color: LinearRgba::from(WATER_COLOR),
+                bg_color: LinearRgba::from(Color::BLACK),
+            },
+            MapTile::Lava => Tile {
+                glyph: '~',
+                fg_color: LinearRgba::from(LAVA_COLOR),
+                bg_color: LinearRgba::from(Color::BLACK),
+            },
+            MapTile::Rubble => Tile {
+                glyph: ':',
+                fg_color: LinearRgba::from(RUBBLE_COLOR),
+                bg_color: LinearRgba::from(Color::BLACK),
+            },
         }
     }
 }
@@ -192,18 +235,7 @@ where
 fn render_full_map(map: &Map, term: &mut Terminal) {
     for x in 0..map.0.width() as i32 {
         for y in 0..map.0.height() as i32 {
-            let tile: Tile = match map.0[ [x as u32, y as u32] ] {
-                MapTile::Wall => Tile {
-                    glyph: '#',
-                    fg_color: LinearRgba::from(WALL_COLOR),
-                    bg_color: LinearRgba::from(Color::BLACK),
-                },
-                MapTile::Floor => Tile {
-                    glyph: '.',
-                    fg_color: LinearRgba::from(FLOOR_COLOR),
-                    bg_color: LinearRgba::from(Color::BLACK),
-                },
-            };
+            let tile = Tile::from(map.0[ [x as u32, y as u32] ]);
             term.put_tile([x as i32, y as i32], tile);
         }
     }
@@ -222,8 +254,10 @@ fn should_render(
     q_entities_changed: Query<(&Renderable, &Position), Changed<Position>>,
     q_map_changed: Query<&Map, Changed<Map>>,
     mut evt_killed: EventReader<ActorKilledEvent>,
+    mut items_removed: RemovedComponents<Item>,
 ) -> bool {
     q_entities_changed.iter().next().is_some()
         || q_map_changed.iter().next().is_some()
         || evt_killed.read().next().is_some()
+        || items_removed.read().next().is_some()
 }
\ No newline at end of file
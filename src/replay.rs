@@ -0,0 +1,476 @@
+//! Turn-by-turn replays.
+//!
+//! Every resolved [Action] is recorded together with the run seed and written
+//! to [REPLAY_FILE]. Starting the game with `--replay <file>` plays a recording
+//! back: the seed, the generated and stored weapons, the starting level and pack are reused and the recorded actions
+//! are fed to the resolver instead of keyboard input and monster AI. Once the recording runs out the
+//! player gets control back.
+
+use std::{collections::VecDeque, fs, path::Path, time::Duration};
+
+use bevy::prelude::*;
+use sark_grids::SizedGrid;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    action::{Action, ActionEvent, ActionResolvedEvent, ResolveActionsSet},
+    dungeon::FloorChange,
+    equipment::{carry_stored_weapons, equip_generated_weapon, Equipment, Slot},
+    combat::{HitPoints, MaxHitPoints},
+    equipment::BaseStats,
+    experience::{Experience, LevelUpEvent, SavedLevel, StatChoice},
+    generating_weapon::{display_weapon_info, GeneratedWeapon},
+    item::{Inventory, ItemKind},
+    map_state::MapActors,
+    movement::Position,
+    player::Player,
+    practice::practicing,
+    rng::{self, RunSeed, SeedChoice},
+    turn_system::TakingATurn,
+    ui::PrintLog,
+    weapon_prediction::bridge::Weapon,
+    AppState, GamePhase,
+};
+
+/// Where the current run is recorded to.
+pub const REPLAY_FILE: &str = "replays/last_run.ron";
+
+/// The recording is written to disk every this many turns, so it survives a crash.
+const AUTOSAVE_TURNS: usize = 50;
+
+/// Delay between the player's turns during playback, so it can be followed.
+const PLAYBACK_STEP: Duration = Duration::from_millis(150);
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayRecorder>()
+            .add_systems(OnEnter(AppState::Lore), start_playback.before(rng::start_run))
+            .add_systems(
+                OnEnter(AppState::WeaponSetup),
+                use_recorded_weapon.before(display_weapon_info).run_if(playing_back),
+            )
+            // Practice runs take turns back, the last real run is kept instead
+            .add_systems(OnEnter(AppState::InGame), start_recording.run_if(not(practicing)))
+            .add_systems(
+                OnEnter(AppState::InGame),
+                (
+                    use_recorded_stored_weapons.after(equip_generated_weapon),
+                    use_recorded_level,
+                    use_recorded_pack,
+                )
+                    .after(start_recording)
+                    .run_if(playing_back),
+            )
+            .add_systems(OnExit(AppState::InGame), save_recording.run_if(not(practicing)))
+            .add_systems(
+                Update,
+                (
+                    playback_system.before(ResolveActionsSet).run_if(playing_back),
+                    record_system.after(ResolveActionsSet).run_if(not(practicing)),
+                )
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// An [Action] with entities swapped for map positions, so it still means the
+/// same thing when the run is played again.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum RecordedAction {
+    Wait,
+    Move([i32; 2]),
+    /// Attack whoever stands on this tile.
+    Attack([i32; 2]),
+    UseStairs(FloorChange),
+    PickUp,
+    UseItem(ItemKind),
+    TakeOff(Slot),
+    /// Switch to the carried weapon with this name, stored weapons load in any order.
+    Wield(String),
+    /// A stat picked in the level up prompt, recorded where the player stood.
+    LevelUp(StatChoice),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RecordedTurn {
+    /// Where the acting actor stood, used to notice a replay going out of sync.
+    pub at: [i32; 2],
+    pub action: RecordedAction,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    /// The run seed as shown in game.
+    pub seed: String,
+    /// The weapon generated for the run, generating it again gives a different one.
+    #[serde(default)]
+    pub weapon: Option<Weapon>,
+    /// Weapons kept from earlier runs, the character may have more by now.
+    #[serde(default)]
+    pub stored_weapons: Vec<Weapon>,
+    /// The character's saved level when the run started, it has moved on since.
+    #[serde(default)]
+    pub level: Option<SavedLevel>,
+    /// The pack the run started with.
+    #[serde(default)]
+    pub pack: Vec<ItemKind>,
+    pub turns: Vec<RecordedTurn>,
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Error reading {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Error parsing {path}: {source}")]
+    Parse {
+        path: String,
+        #[source]
+        source: ron::error::SpannedError,
+    },
+    #[error("{path} has an invalid seed `{seed}`")]
+    Seed { path: String, seed: String },
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+        let bytes = fs::read(path).map_err(|source| ReplayError::Io {
+            path: path.display().to_string(),
+            source,
+        })?;
+
+        let replay: Replay = ron::de::from_bytes(&bytes).map_err(|source| ReplayError::Parse {
+            path: path.display().to_string(),
+            source,
+        })?;
+
+        if RunSeed::parse(&replay.seed).is_none() {
+            return Err(ReplayError::Seed {
+                path: path.display().to_string(),
+                seed: replay.seed,
+            });
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = ron::ser::to_string(self).map_err(std::io::Error::other)?;
+        fs::write(path, text)
+    }
+}
+
+/// A replay waiting for the next run to start, set from the command line.
+#[derive(Resource)]
+pub struct PendingReplay(pub Replay);
+
+/// The turns left to play back.
+#[derive(Resource)]
+pub struct Playback {
+    weapon: Option<Weapon>,
+    stored_weapons: Vec<Weapon>,
+    level: Option<SavedLevel>,
+    pack: Vec<ItemKind>,
+    turns: VecDeque<RecordedTurn>,
+    timer: Timer,
+}
+
+/// The run recorded so far.
+#[derive(Resource, Default)]
+pub struct ReplayRecorder(pub Replay);
+
+pub fn playing_back(playback: Option<Res<Playback>>) -> bool {
+    playback.is_some()
+}
+
+fn start_playback(
+    mut commands: Commands,
+    pending: Option<Res<PendingReplay>>,
+    mut seed: ResMut<SeedChoice>,
+) {
+    let Some(pending) = pending else {
+        return;
+    };
+    commands.remove_resource::<PendingReplay>();
+
+    seed.0 = RunSeed::parse(&pending.0.seed);
+    commands.insert_resource(Playback {
+        weapon: pending.0.weapon.clone(),
+        stored_weapons: pending.0.stored_weapons.clone(),
+        level: pending.0.level,
+        pack: pending.0.pack.clone(),
+        turns: pending.0.turns.iter().cloned().collect(),
+        timer: Timer::new(PLAYBACK_STEP, TimerMode::Repeating),
+    });
+    info!("Playing back {} turns with seed {}", pending.0.turns.len(), pending.0.seed);
+}
+
+/// Swaps the freshly generated weapon for the one the replay was recorded with.
+fn use_recorded_weapon(playback: Res<Playback>, weapon: Option<ResMut<GeneratedWeapon>>) {
+    let Some(recorded) = &playback.weapon else {
+        warn!("The replay has no weapon, playing back with a new one");
+        return;
+    };
+    if let Some(mut weapon) = weapon {
+        weapon.0 = recorded.clone();
+    }
+}
+
+/// Carries the recorded weapons instead of the ones stored now.
+fn use_recorded_stored_weapons(
+    playback: Res<Playback>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut q_equipment: Query<&mut Equipment, With<Player>>,
+) {
+    if let Ok(mut equipment) = q_equipment.single_mut() {
+        carry_stored_weapons(&mut equipment, &playback.stored_weapons);
+    }
+    recorder.0.stored_weapons = playback.stored_weapons.clone();
+}
+
+/// Starts the player from the recorded level instead of the saved one.
+fn use_recorded_level(
+    playback: Res<Playback>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut q_player: Query<(&mut Experience, &mut HitPoints, &mut MaxHitPoints, &mut BaseStats), With<Player>>,
+) {
+    let Some(recorded) = playback.level else {
+        return;
+    };
+    if let Ok((mut experience, mut hp, mut max_hp, mut base)) = q_player.single_mut() {
+        recorded.apply(&mut experience, &mut hp, &mut max_hp, &mut base);
+    }
+    recorder.0.level = Some(recorded);
+}
+
+/// Starts the player with the recorded pack instead of the saved one.
+fn use_recorded_pack(
+    playback: Res<Playback>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut q_inventory: Query<&mut Inventory, With<Player>>,
+) {
+    if let Ok(mut inventory) = q_inventory.single_mut() {
+        inventory.0 = playback.pack.clone();
+    }
+    recorder.0.pack = playback.pack.clone();
+}
+
+fn start_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    seed: Res<RunSeed>,
+    weapon: Option<Res<GeneratedWeapon>>,
+) {
+    recorder.0 = Replay {
+        seed: seed.to_code(),
+        weapon: weapon.map(|w| w.0.clone()),
+        stored_weapons: Vec::new(),
+        level: None,
+        pack: Vec::new(),
+        turns: Vec::new(),
+    };
+}
+
+fn save_recording(recorder: Res<ReplayRecorder>) {
+    write_recording(&recorder.0);
+}
+
+fn write_recording(replay: &Replay) {
+    match replay.save(Path::new(REPLAY_FILE)) {
+        Ok(()) => info!("Saved replay of {} turns to {}", replay.turns.len(), REPLAY_FILE),
+        Err(e) => error!("Failed to save replay to {}: {}", REPLAY_FILE, e),
+    }
+}
+
+fn record_system(
+    mut recorder: ResMut<ReplayRecorder>,
+    mut evt_resolved: EventReader<ActionResolvedEvent>,
+    mut evt_level_up: EventReader<LevelUpEvent>,
+    q_positions: Query<&Position>,
+    q_player: Query<&Position, With<Player>>,
+    q_equipment: Query<&Equipment>,
+) {
+    let mut recorded = false;
+
+    // Turns are paused while levelling up, so these never mix with actions
+    if let Ok(pos) = q_player.single() {
+        for LevelUpEvent(choice) in evt_level_up.read() {
+            recorder.0.turns.push(RecordedTurn {
+                at: pos.0.to_array(),
+                action: RecordedAction::LevelUp(*choice),
+            });
+            recorded = true;
+        }
+    }
+
+    for ev in evt_resolved.read() {
+        let action = match ev.action {
+            Action::Wait => RecordedAction::Wait,
+            Action::Move(dir) => RecordedAction::Move(dir.to_array()),
+            Action::UseStairs(change) => RecordedAction::UseStairs(change),
+            Action::PickUp => RecordedAction::PickUp,
+            Action::UseItem(kind) => RecordedAction::UseItem(kind),
+            Action::TakeOff(slot) => RecordedAction::TakeOff(slot),
+            Action::Wield(index) => match q_equipment.get(ev.actor).ok().and_then(|e| e.weapons.get(index)) {
+                Some(weapon) => RecordedAction::Wield(weapon.name.clone()),
+                None => RecordedAction::Wait,
+            },
+            Action::Attack(target) => match q_positions.get(target) {
+                Ok(pos) => RecordedAction::Attack(pos.0.to_array()),
+                Err(_) => RecordedAction::Wait,
+            },
+        };
+
+        recorder.0.turns.push(RecordedTurn {
+            at: ev.from.to_array(),
+            action,
+        });
+        recorded = true;
+    }
+
+    if recorded && recorder.0.turns.len() % AUTOSAVE_TURNS == 0 {
+        write_recording(&recorder.0);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn playback_system(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut log: ResMut<PrintLog>,
+    mut evt_action: EventWriter<ActionEvent>,
+    mut evt_level_up: EventWriter<LevelUpEvent>,
+    q_acting: Query<(Entity, &Position, Has<Player>), With<TakingATurn>>,
+    q_player: Query<&Position, With<Player>>,
+    q_equipment: Query<&Equipment>,
+    actors: Res<MapActors>,
+    phase: Res<State<GamePhase>>,
+    time: Res<Time>,
+) {
+    let Ok((entity, pos, is_player)) = q_acting.single() else {
+        return;
+    };
+
+    // Monsters act right away, the player's turns are spaced out to be watchable
+    if is_player && !playback.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let Some(turn) = playback.turns.pop_front() else {
+        commands.remove_resource::<Playback>();
+        log.push("Replay finished, you're in control.".to_string());
+        return;
+    };
+
+    // Level ups happen in their own phase and belong to the player, whoever is acting
+    let levelling_up = *phase.get() == GamePhase::LevelUp;
+    let (is_level_up, at) = match turn.action {
+        RecordedAction::LevelUp(_) => (true, q_player.single().map_or(pos.0, |p| p.0)),
+        _ => (false, pos.0),
+    };
+
+    if turn.at != at.to_array() || is_level_up != levelling_up {
+        warn!("Replay out of sync: expected {:?} at {:?}, {:?} is at {}", turn.action, turn.at, entity, pos.0);
+        commands.remove_resource::<Playback>();
+        log.push("Replay went out of sync and was stopped.".to_string());
+        return;
+    }
+
+    let action = match turn.action {
+        RecordedAction::LevelUp(choice) => {
+            evt_level_up.write(LevelUpEvent(choice));
+            return;
+        }
+        RecordedAction::Wait => Action::Wait,
+        RecordedAction::Move(dir) => Action::Move(IVec2::from_array(dir)),
+        RecordedAction::UseStairs(change) => Action::UseStairs(change),
+        RecordedAction::PickUp => Action::PickUp,
+        RecordedAction::UseItem(kind) => Action::UseItem(kind),
+        RecordedAction::TakeOff(slot) => Action::TakeOff(slot),
+        RecordedAction::Wield(name) => {
+            let carried = q_equipment.get(entity).ok();
+            let Some(index) = carried.and_then(|e| e.weapons.iter().position(|w| w.name == name)) else {
+                warn!("Replay out of sync: {:?} doesn't carry {}", entity, name);
+                commands.remove_resource::<Playback>();
+                log.push("Replay went out of sync and was stopped.".to_string());
+                return;
+            };
+            Action::Wield(index)
+        }
+        RecordedAction::Attack(at) => {
+            let at = IVec2::from_array(at);
+            match actors.0.in_bounds(at).then(|| actors.0[at]).flatten() {
+                Some(target) => Action::Attack(target),
+                None => Action::Wait,
+            }
+        }
+    };
+
+    evt_action.write(ActionEvent {
+        actor: entity,
+        action,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        dungeon::FloorChange,
+        experience::{SavedLevel, StatChoice},
+        item::ItemKind,
+        weapon_prediction::bridge::Weapon,
+    };
+
+    use super::{RecordedAction, RecordedTurn, Replay};
+
+    #[test]
+    fn replays_round_trip_through_ron() {
+        let replay = Replay {
+            seed: "3W5E11264SGSF".to_string(),
+            weapon: Some(Weapon {
+                name: "Rusty Cleaver".to_string(),
+                damage: 14,
+                weight: 7.5,
+                upgrade: "None".to_string(),
+                perk: "Bleed".to_string(),
+                weapon_type: "Axe".to_string(),
+                predicted_price: Some(120.0),
+            }),
+            stored_weapons: vec![Weapon {
+                name: "Bent Pike".to_string(),
+                damage: 9,
+                weight: 6.0,
+                upgrade: "Sharpened".to_string(),
+                perk: "None".to_string(),
+                weapon_type: "Spear".to_string(),
+                predicted_price: None,
+            }],
+            level: Some(SavedLevel {
+                level: 3,
+                max_hp: 70,
+                defense: 2,
+                strength: 3,
+            }),
+            pack: vec![ItemKind::HealingDraught, ItemKind::HealingDraught, ItemKind::ChainMail],
+            turns: vec![
+                RecordedTurn { at: [4, 5], action: RecordedAction::Move([1, 0]) },
+                RecordedTurn { at: [5, 5], action: RecordedAction::Attack([6, 5]) },
+                RecordedTurn { at: [9, 2], action: RecordedAction::Wait },
+                RecordedTurn { at: [5, 5], action: RecordedAction::UseStairs(FloorChange::Down) },
+                RecordedTurn { at: [3, 7], action: RecordedAction::PickUp },
+                RecordedTurn { at: [3, 7], action: RecordedAction::Wield("Bent Pike".to_string()) },
+                RecordedTurn { at: [3, 7], action: RecordedAction::LevelUp(StatChoice::Strength) },
+            ],
+        };
+
+        let text = ron::ser::to_string(&replay).unwrap();
+        assert_eq!(replay, ron::de::from_str(&text).unwrap());
+    }
+}
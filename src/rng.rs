@@ -1,5 +1,87 @@
+use bevy::prelude::*;
 use bracket_random::prelude::{RandomNumberGenerator, DiceType};
+use rand::{prelude::StdRng, Rng, SeedableRng};
 
+use crate::{map::MapGenSetupSet, AppState};
+
+/// Plugin picking the [RunSeed] at the start of every run and seeding the
+/// random streams derived from it.
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeedChoice>()
+            .init_resource::<RunSeed>()
+            .init_resource::<CombatRng>()
+            .init_resource::<LootRng>()
+            .add_systems(OnEnter(AppState::Lore), start_run.before(MapGenSetupSet));
+    }
+}
+
+/// The seed every random stream of a run is derived from, so the same seed
+/// replays the same dungeon, spawns and dice rolls.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunSeed(pub u64);
+
+impl Default for RunSeed {
+    fn default() -> Self {
+        Self(rand::rng().random())
+    }
+}
+
+/// The independent random streams of a run. Each stream gets its own seed so
+/// that, for example, an extra combat roll doesn't change the next floor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngStream {
+    Map,
+    Monsters,
+    Combat,
+    Loot,
+    Items,
+}
+
+const SEED_DIGITS: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+impl RunSeed {
+    /// The seed for one of the run's streams.
+    pub fn stream(&self, stream: RngStream) -> u64 {
+        splitmix64(self.0 ^ (stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+
+    /// Short base 36 code for sharing the seed, e.g. `3W5E11264SGSF`.
+    pub fn to_code(&self) -> String {
+        let mut n = self.0;
+        let mut digits = Vec::new();
+        loop {
+            digits.push(SEED_DIGITS[(n % 36) as usize] as char);
+            n /= 36;
+            if n == 0 {
+                break;
+            }
+        }
+        digits.iter().rev().collect()
+    }
+
+    /// Reads a code made by [RunSeed::to_code], ignoring case, spaces and dashes.
+    pub fn parse(code: &str) -> Option<RunSeed> {
+        let code: String = code.chars().filter(|c| !c.is_whitespace() && *c != '-').collect();
+        if code.is_empty() {
+            return None;
+        }
+        u64::from_str_radix(&code, 36).ok().map(RunSeed)
+    }
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// The seed picked in the main menu for the next run, a random one is rolled if empty.
+#[derive(Resource, Default)]
+pub struct SeedChoice(pub Option<RunSeed>);
 
 pub struct DiceRng {
     rng: RandomNumberGenerator,
@@ -7,18 +89,75 @@ pub struct DiceRng {
 
 impl Default for DiceRng {
     fn default() -> Self {
-        Self { 
+        Self {
             rng: RandomNumberGenerator::new(),
         }
     }
 }
 
 impl DiceRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            rng: RandomNumberGenerator::seeded(seed),
+        }
+    }
+
     pub fn roll(&mut self, dice: DiceType) -> i32 {
-        self.rng.roll(dice) 
+        self.rng.roll(dice)
     }
 
     // pub fn roll_dice(&mut self, count: i32, faces: i32) -> i32 {
     //     self.rng.roll_dice(count, faces)
     // }
-}
\ No newline at end of file
+}
+
+/// Dice rolled in combat, shared by the player and monsters.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct CombatRng(pub DiceRng);
+
+/// Drops and other rewards.
+#[derive(Resource, Deref, DerefMut)]
+pub struct LootRng(pub StdRng);
+
+impl Default for LootRng {
+    fn default() -> Self {
+        Self(StdRng::from_os_rng())
+    }
+}
+
+pub fn start_run(
+    mut choice: ResMut<SeedChoice>,
+    mut seed: ResMut<RunSeed>,
+    mut combat: ResMut<CombatRng>,
+    mut loot: ResMut<LootRng>,
+) {
+    *seed = choice.0.take().unwrap_or_default();
+    combat.0 = DiceRng::seeded(seed.stream(RngStream::Combat));
+    loot.0 = StdRng::seed_from_u64(seed.stream(RngStream::Loot));
+
+    info!("Starting run with seed {}", seed.to_code());
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RngStream, RunSeed};
+
+    #[test]
+    fn seed_codes_round_trip() {
+        for seed in [0, 5, 1234567890, u64::MAX] {
+            let seed = RunSeed(seed);
+            assert_eq!(Some(seed), RunSeed::parse(&seed.to_code()));
+        }
+        assert_eq!(Some(RunSeed(36 * 36 + 10)), RunSeed::parse("1-0a"));
+        assert_eq!(None, RunSeed::parse("not a seed!"));
+        assert_eq!(None, RunSeed::parse(""));
+    }
+
+    #[test]
+    fn streams_are_independent() {
+        let seed = RunSeed(5);
+        assert_ne!(seed.stream(RngStream::Map), seed.stream(RngStream::Monsters));
+        assert_ne!(seed.stream(RngStream::Combat), seed.stream(RngStream::Loot));
+        assert_eq!(seed.stream(RngStream::Map), RunSeed(5).stream(RngStream::Map));
+    }
+}
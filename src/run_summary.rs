@@ -0,0 +1,262 @@
+//! Statistics gathered over a run, shown and saved once it ends in
+//! [GamePhase::GameOver] or [GamePhase::Victory].
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_ascii_terminal::{color, terminal::Terminal, StringDecorator};
+
+use crate::{
+    action::ActionResolvedEvent,
+    combat::{ActorKilledEvent, DamageEvent, DeathSystemSet},
+    dbs::{psqldb::Database, rundb::RunSummaryDb},
+    dungeon::DungeonLevel,
+    generating_weapon::GeneratedWeapon,
+    main_menu::CharacterName,
+    player::Player,
+    practice::PracticeMode,
+    render::RenderSystemSet,
+    replay::Playback,
+    rng::RunSeed,
+    AppState, GamePhase, GlobalTerminal,
+};
+
+pub struct RunSummaryPlugin;
+
+impl Plugin for RunSummaryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .add_systems(OnEnter(AppState::InGame), start_run_stats)
+            .add_systems(OnExit(AppState::InGame), clear_summary)
+            .add_systems(OnEnter(GamePhase::GameOver), finish_run)
+            .add_systems(OnEnter(GamePhase::Victory), finish_run)
+            .add_systems(
+                PostUpdate,
+                track_stats_system
+                    .after(DeathSystemSet)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                draw_summary
+                    .after(RenderSystemSet)
+                    .run_if(in_state(AppState::InGame).and(resource_exists::<RunSummary>)),
+            );
+    }
+}
+
+/// Running totals for the current run.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct RunStats {
+    /// Turns the player has taken.
+    pub turns: u32,
+    /// Monsters killed, by name.
+    pub kills: BTreeMap<String, u32>,
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunOutcome {
+    Died,
+    Won,
+}
+
+impl RunOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RunOutcome::Died => "died",
+            RunOutcome::Won => "won",
+        }
+    }
+}
+
+/// How a finished run went, shown until the player leaves the game.
+#[derive(Resource, Debug)]
+pub struct RunSummary {
+    pub outcome: RunOutcome,
+    pub stats: RunStats,
+    /// The floor the run ended on, counting from 1.
+    pub floor: u32,
+    pub weapon: String,
+    pub seed: String,
+}
+
+fn start_run_stats(mut stats: ResMut<RunStats>) {
+    *stats = RunStats::default();
+}
+
+fn clear_summary(mut commands: Commands) {
+    commands.remove_resource::<RunSummary>();
+}
+
+fn track_stats_system(
+    mut stats: ResMut<RunStats>,
+    mut evt_resolved: EventReader<ActionResolvedEvent>,
+    mut evt_damage: EventReader<DamageEvent>,
+    mut evt_killed: EventReader<ActorKilledEvent>,
+    q_player: Query<(), With<Player>>,
+) {
+    for ev in evt_resolved.read() {
+        if q_player.contains(ev.actor) {
+            stats.turns += 1;
+        }
+    }
+
+    for ev in evt_damage.read() {
+        if q_player.contains(ev.target) {
+            stats.damage_taken += ev.amount;
+        } else if q_player.contains(ev.actor) {
+            stats.damage_dealt += ev.amount;
+        }
+    }
+
+    for ev in evt_killed.read().filter(|ev| !ev.player) {
+        *stats.kills.entry(ev.name.clone()).or_default() += 1;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn finish_run(
+    mut commands: Commands,
+    mut stats: ResMut<RunStats>,
+    phase: Res<State<GamePhase>>,
+    level: Res<DungeonLevel>,
+    seed: Res<RunSeed>,
+    weapon: Option<Res<GeneratedWeapon>>,
+    name: Res<CharacterName>,
+    practice: Res<PracticeMode>,
+    psql: Res<Database>,
+    playback: Option<Res<Playback>>,
+) {
+    let outcome = match phase.get() {
+        GamePhase::Victory => RunOutcome::Won,
+        _ => RunOutcome::Died,
+    };
+
+    let summary = RunSummary {
+        outcome,
+        stats: std::mem::take(&mut *stats),
+        floor: level.depth + 1,
+        weapon: weapon.map_or_else(|| "None".to_string(), |w| w.0.name.clone()),
+        seed: seed.to_code(),
+    };
+    info!("Run {}: {:?}", outcome.as_str(), summary);
+
+    // Watching a replay doesn't count as another run
+    if playback.is_none() {
+        let record = RunSummaryDb {
+            player_name: name.0.clone(),
+            outcome: outcome.as_str().to_string(),
+            turns: summary.stats.turns as i32,
+            floor: summary.floor as i32,
+            damage_dealt: summary.stats.damage_dealt,
+            damage_taken: summary.stats.damage_taken,
+            weapon: summary.weapon.clone(),
+            seed: summary.seed.clone(),
+            practice: practice.0,
+            kills: summary.stats.kills.iter().map(|(name, n)| (name.clone(), *n as i32)).collect(),
+        };
+
+        let db_client = psql.client.clone();
+
+        bevy::tasks::IoTaskPool::get().spawn(async move {
+            match RunSummaryDb::create(db_client, &record).await {
+                Ok(id) => println!("!!! Saved run summary to DB: {}", id),
+                Err(e) => eprintln!(">>X<< Failed to save run summary: {}", e),
+            }
+        }).detach();
+    }
+
+    commands.insert_resource(summary);
+}
+
+fn draw_summary(
+    summary: Res<RunSummary>,
+    mut q_term: Query<&mut Terminal, With<GlobalTerminal>>,
+) {
+    let Ok(mut term) = q_term.single_mut() else {
+        return;
+    };
+    let width = term.width() as usize;
+
+    term.clear();
+
+    let (title, title_color) = match summary.outcome {
+        RunOutcome::Died => ("YOU DIED", color::RED),
+        RunOutcome::Won => ("VICTORY", color::YELLOW),
+    };
+    let title = format!(" {} ", title);
+    term.put_string([0, 2], format!("{:=^width$}", title).fg(title_color));
+
+    let stats = &summary.stats;
+    let lines = [
+        format!("Floor reached:  {}", summary.floor),
+        format!("Turns survived: {}", stats.turns),
+        format!("Damage dealt:   {}", stats.damage_dealt),
+        format!("Damage taken:   {}", stats.damage_taken),
+        format!("Weapon:         {}", summary.weapon),
+        format!("Seed:           {}", summary.seed),
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        term.put_string([4, 5 + i as i32], line.as_str().fg(color::WHITE));
+    }
+
+    let mut y = 6 + lines.len() as i32;
+    term.put_string([4, y], "Kills:".fg(color::WHITE));
+    if stats.kills.is_empty() {
+        term.put_string([6, y + 1], "none".fg(color::GRAY));
+        y += 1;
+    }
+    for (name, count) in stats.kills.iter() {
+        y += 1;
+        term.put_string([6, y], format!("{} x{}", name, count).fg(color::GRAY));
+    }
+
+    term.put_string([0, y + 3], format!("{:^width$}", "[Esc] Return to the menu").fg(color::GREEN));
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{ecs::system::RunSystemOnce, prelude::*};
+
+    use super::{track_stats_system, RunStats};
+    use crate::{
+        action::ActionResolvedEvent,
+        combat::{ActorKilledEvent, DamageEvent},
+        player::Player,
+    };
+
+    #[test]
+    fn damage_is_split_into_dealt_and_taken() {
+        let mut app = App::new();
+        app.init_resource::<RunStats>()
+            .add_event::<ActionResolvedEvent>()
+            .add_event::<DamageEvent>()
+            .add_event::<ActorKilledEvent>()
+            .add_systems(Update, track_stats_system);
+
+        let player = app.world_mut().spawn(Player).id();
+        let monster = app.world_mut().spawn_empty().id();
+
+        // Lava and status ticks are self-targeted
+        let hits = [
+            (player, monster, 5),
+            (monster, player, 3),
+            (player, player, 4),
+            (monster, monster, 2),
+        ];
+        app.world_mut()
+            .run_system_once(move |mut evt_damage: EventWriter<DamageEvent>| {
+                for (actor, target, amount) in hits {
+                    evt_damage.write(DamageEvent { actor, target, amount });
+                }
+            })
+            .unwrap();
+        app.update();
+
+        let stats = app.world().resource::<RunStats>();
+        assert_eq!(5, stats.damage_dealt);
+        assert_eq!(7, stats.damage_taken);
+    }
+}
@@ -5,6 +5,7 @@ use bevy::math::IVec2;
 /// A rectangle on a grid.
 ///
 /// Points contained in the rect can be iterated over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Rect {
     pub min: IVec2,
     pub max: IVec2,
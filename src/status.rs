@@ -0,0 +1,256 @@
+//! Timed status effects like poison and stuns.
+//!
+//! Effects last a number of the affected actor's turns and act at the end of
+//! each of them, dealing damage or healing through [TargetEvent]s. A stunned
+//! actor's action is replaced with waiting.
+
+use bevy::prelude::*;
+
+use crate::{
+    combat::{ActorEffect, TargetEvent},
+    turn_system::{turns_running, TakingATurn, TurnEndSet},
+};
+
+/// Chance in percent for a weapon perk to apply its effect on a hit.
+pub const PERK_CHANCE: i32 = 25;
+
+/// Cap for effects that intensify when applied again.
+const MAX_POTENCY: i32 = 5;
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            tick_status_system
+                .after(TurnEndSet)
+                .run_if(turns_running),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusKind {
+    Poison,
+    Bleed,
+    Stun,
+    Burning,
+    Regeneration,
+}
+
+/// What happens when an effect is applied to an actor that already has it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stacking {
+    /// Potency adds up to [MAX_POTENCY], the duration starts over.
+    Intensify,
+    /// The durations add up.
+    Extend,
+    /// The stronger potency and longer duration are kept.
+    Refresh,
+}
+
+impl StatusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "poison",
+            StatusKind::Bleed => "bleed",
+            StatusKind::Stun => "stun",
+            StatusKind::Burning => "burning",
+            StatusKind::Regeneration => "regeneration",
+        }
+    }
+
+    /// Completes "<name> ..." when the effect is applied.
+    pub fn applied(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "is poisoned",
+            StatusKind::Bleed => "starts bleeding",
+            StatusKind::Stun => "is stunned",
+            StatusKind::Burning => "catches fire",
+            StatusKind::Regeneration => "starts regenerating",
+        }
+    }
+
+    fn stacking(&self) -> Stacking {
+        match self {
+            StatusKind::Poison => Stacking::Intensify,
+            StatusKind::Bleed => Stacking::Extend,
+            StatusKind::Stun | StatusKind::Burning | StatusKind::Regeneration => Stacking::Refresh,
+        }
+    }
+
+    /// The effect as weapons and hazards usually apply it.
+    pub fn effect(&self) -> StatusEffect {
+        let (turns, potency) = match self {
+            StatusKind::Poison => (4, 2),
+            StatusKind::Bleed => (3, 3),
+            StatusKind::Stun => (1, 0),
+            StatusKind::Burning => (3, 3),
+            StatusKind::Regeneration => (5, 2),
+        };
+        StatusEffect {
+            kind: *self,
+            turns,
+            potency,
+        }
+    }
+
+    /// Helpful effects go on whoever caused them instead of the target.
+    pub fn is_beneficial(&self) -> bool {
+        *self == StatusKind::Regeneration
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Turns of the affected actor left.
+    pub turns: u32,
+    /// Damage or healing per turn.
+    pub potency: i32,
+}
+
+/// The effects currently on an actor.
+#[derive(Component, Default, Debug, Clone)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.0.iter().any(|e| e.kind == kind)
+    }
+
+    pub fn apply(&mut self, effect: StatusEffect) {
+        let Some(current) = self.0.iter_mut().find(|e| e.kind == effect.kind) else {
+            self.0.push(effect);
+            return;
+        };
+
+        match effect.kind.stacking() {
+            Stacking::Intensify => {
+                current.potency = (current.potency + effect.potency).min(MAX_POTENCY);
+                current.turns = current.turns.max(effect.turns);
+            }
+            Stacking::Extend => current.turns += effect.turns,
+            Stacking::Refresh => {
+                current.potency = current.potency.max(effect.potency);
+                current.turns = current.turns.max(effect.turns);
+            }
+        }
+    }
+
+    /// Uses up a turn of every effect, returning the effects that were active.
+    fn tick(&mut self) -> Vec<StatusEffect> {
+        let active = self.0.clone();
+        for effect in self.0.iter_mut() {
+            effect.turns = effect.turns.saturating_sub(1);
+        }
+        self.0.retain(|e| e.turns > 0);
+        active
+    }
+}
+
+/// A status effect an actor's hits can cause.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct OnHitStatus {
+    pub effect: StatusEffect,
+    /// Chance in percent per hit.
+    pub chance: i32,
+}
+
+/// Word beginnings in weapon perks and the effects they stand for.
+const PERK_KEYWORDS: &[(&str, StatusKind)] = &[
+    ("poison", StatusKind::Poison),
+    ("venom", StatusKind::Poison),
+    ("toxic", StatusKind::Poison),
+    ("frost", StatusKind::Stun),
+    ("ice", StatusKind::Stun),
+    ("shock", StatusKind::Stun),
+    ("thunder", StatusKind::Stun),
+    ("stun", StatusKind::Stun),
+    ("fire", StatusKind::Burning),
+    ("flam", StatusKind::Burning),
+    ("burn", StatusKind::Burning),
+    ("ember", StatusKind::Burning),
+    ("blaze", StatusKind::Burning),
+    ("bleed", StatusKind::Bleed),
+    ("blood", StatusKind::Bleed),
+    ("serrat", StatusKind::Bleed),
+    ("cleave", StatusKind::Bleed),
+    ("rend", StatusKind::Bleed),
+    ("life", StatusKind::Regeneration),
+    ("vampir", StatusKind::Regeneration),
+    ("regen", StatusKind::Regeneration),
+    ("heal", StatusKind::Regeneration),
+];
+
+/// Reads a generated weapon perk, like `Frostbite Cleave`, as the status
+/// effect its hits cause. The first word that means something wins.
+pub fn perk_status(perk: &str) -> Option<StatusKind> {
+    perk.split(|c: char| !c.is_alphabetic())
+        .map(str::to_lowercase)
+        .find_map(|word| {
+            PERK_KEYWORDS
+                .iter()
+                .find(|(keyword, _)| word.starts_with(keyword))
+                .map(|(_, kind)| *kind)
+        })
+}
+
+fn tick_status_system(
+    mut ended_turns: RemovedComponents<TakingATurn>,
+    mut q_effects: Query<&mut StatusEffects>,
+    mut evt_target: EventWriter<TargetEvent>,
+) {
+    for entity in ended_turns.read() {
+        // Despawned actors end their turn too
+        let Ok(mut effects) = q_effects.get_mut(entity) else {
+            continue;
+        };
+
+        for effect in effects.tick() {
+            if effect.kind == StatusKind::Stun {
+                continue;
+            }
+            evt_target.write(TargetEvent {
+                actor: entity,
+                target: entity,
+                effect: ActorEffect::Status(effect.kind, effect.potency),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{perk_status, StatusEffects, StatusKind};
+
+    #[test]
+    fn effects_stack_by_kind() {
+        let mut effects = StatusEffects::default();
+
+        // Poison gets stronger, bleeding lasts longer, stuns don't add up
+        for kind in [StatusKind::Poison, StatusKind::Bleed, StatusKind::Stun] {
+            effects.apply(kind.effect());
+            effects.apply(kind.effect());
+        }
+        let find = |kind| *effects.0.iter().find(|e| e.kind == kind).unwrap();
+
+        assert_eq!((4, 4), (find(StatusKind::Poison).turns, find(StatusKind::Poison).potency));
+        assert_eq!((6, 3), (find(StatusKind::Bleed).turns, find(StatusKind::Bleed).potency));
+        assert_eq!(1, find(StatusKind::Stun).turns);
+
+        // The stun runs out after one turn
+        effects.tick();
+        assert!(!effects.has(StatusKind::Stun));
+        assert!(effects.has(StatusKind::Poison));
+    }
+
+    #[test]
+    fn perks_map_to_effects() {
+        assert_eq!(Some(StatusKind::Stun), perk_status("Frostbite Cleave"));
+        assert_eq!(Some(StatusKind::Bleed), perk_status("Serrated edge"));
+        assert_eq!(Some(StatusKind::Regeneration), perk_status("Lifesteal"));
+        assert_eq!(None, perk_status("Slice of Justice"));
+    }
+}
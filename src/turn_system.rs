@@ -1,5 +1,10 @@
 use bevy::prelude::*;
-use crate::AppState;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::{player::Player, GamePhase};
 
 pub struct TurnSystemPlugin;
 
@@ -12,17 +17,38 @@ pub struct TurnEndSet;
 impl Plugin for TurnSystemPlugin {
     fn build(&self, app: &mut App) {
         app
-            .configure_sets(Update, TurnBeginSet.run_if(in_state(AppState::InGame)))
-            .configure_sets(Update, TurnEndSet.after(TurnBeginSet).run_if(in_state(AppState::InGame)))
-            .add_systems(Update, turn_begin_system.in_set(TurnBeginSet))
+            .init_resource::<TurnQueue>()
+            .configure_sets(Update, TurnBeginSet.run_if(turns_running))
+            .configure_sets(Update, TurnEndSet.after(TurnBeginSet).run_if(turns_running))
+            .add_systems(Update, (schedule_actors_system, turn_begin_system, turn_phase_system).chain().in_set(TurnBeginSet))
             .add_systems(Update, turn_end_system.in_set(TurnEndSet));
     }
 }
 
+/// Turns advance while someone is acting, not while the player is in a menu
+/// or the run is over.
+pub fn turns_running(phase: Option<Res<State<GamePhase>>>) -> bool {
+    matches!(phase.map(|p| *p.get()), Some(GamePhase::PlayerTurn | GamePhase::MonsterTurn))
+}
+
+/// Energy an actor needs to be given a turn.
+pub const TURN_ENERGY: i32 = 100;
+
+/// Energy spent by a regular action like moving or attacking.
+pub const ACTION_COST: i32 = 100;
+
 /// When an actor's energy reaches or exceeds 100, it will be given a turn.
 #[derive(Default, Debug, Component)]
 pub struct Energy(pub i32);
 
+impl Energy {
+    /// Pays for an action. Cheap actions can leave enough energy to act again
+    /// right away, expensive ones push the actor's next turn further back.
+    pub fn spend(&mut self, cost: i32) {
+        self.0 -= cost;
+    }
+}
+
 /// Determines how frequently an actor gets to take their turn,
 /// relative to other actors.
 #[derive(Debug, Component)]
@@ -36,35 +62,203 @@ pub struct Actor;
 #[derive(Debug, Component)]
 pub struct TakingATurn;
 
+/// Actors ordered by the time their next turn comes up.
+///
+/// Time advances in ticks, every tick an actor gains [Speed] energy. Instead of
+/// ticking everyone until someone reaches [TURN_ENERGY], each actor is queued
+/// at the tick it will get there.
+#[derive(Resource, Default)]
+pub struct TurnQueue {
+    now: u64,
+    next_seq: u64,
+    /// (time, seq, entity), seq keeps actors queued for the same tick in order.
+    heap: BinaryHeap<Reverse<(u64, u64, Entity)>>,
+    /// The live entry of every queued actor, anything else in the heap is stale.
+    scheduled: HashMap<Entity, Scheduled>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Scheduled {
+    seq: u64,
+    queued_at: u64,
+    speed: i32,
+}
+
+impl TurnQueue {
+    /// The tick of the turn currently being taken.
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Queues `entity` for the tick its energy reaches [TURN_ENERGY],
+    /// replacing any earlier entry. Actors without speed never act.
+    pub fn schedule(&mut self, entity: Entity, energy: i32, speed: i32) {
+        if speed <= 0 {
+            self.remove(entity);
+            return;
+        }
+
+        let missing = (TURN_ENERGY - energy).max(0) as u64;
+        let time = self.now + missing.div_ceil(speed as u64);
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        self.heap.push(Reverse((time, seq, entity)));
+        self.scheduled.insert(entity, Scheduled { seq, queued_at: self.now, speed });
+    }
+
+    /// Energy `entity` has gained so far while waiting in the queue.
+    pub fn gained(&self, entity: Entity) -> i32 {
+        self.scheduled
+            .get(&entity)
+            .map_or(0, |live| live.speed * (self.now - live.queued_at) as i32)
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        self.scheduled.remove(&entity);
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.scheduled.contains_key(&entity)
+    }
+
+    /// Advances time to the next queued actor and returns it with the
+    /// number of ticks it waited.
+    pub fn pop(&mut self) -> Option<(Entity, u64)> {
+        while let Some(Reverse((time, seq, entity))) = self.heap.pop() {
+            match self.scheduled.get(&entity) {
+                Some(live) if live.seq == seq => {
+                    let waited = time - live.queued_at;
+                    self.scheduled.remove(&entity);
+                    self.now = time;
+                    return Some((entity, waited));
+                }
+                _ => continue,
+            }
+        }
+        None
+    }
+}
+
+/// Queues actors that were just spawned or whose speed changed between turns.
+fn schedule_actors_system(
+    mut queue: ResMut<TurnQueue>,
+    mut q_new: Query<(Entity, &mut Energy, &Speed), (With<Actor>, Without<TakingATurn>, Or<(Added<Actor>, Changed<Speed>)>)>,
+    mut removed: RemovedComponents<Actor>,
+) {
+    for entity in removed.read() {
+        queue.remove(entity);
+    }
+
+    for (entity, mut energy, speed) in q_new.iter_mut() {
+        // Keep what was gained at the old speed, only the rest of the wait changes
+        energy.0 += queue.gained(entity);
+        queue.schedule(entity, energy.0, speed.0);
+    }
+}
+
 fn turn_begin_system(
     mut commands: Commands,
-    mut q_waiting_actors: Query<(Entity, &mut Energy, &Speed), (With<Actor>, Without<TakingATurn>)>,
+    mut queue: ResMut<TurnQueue>,
+    mut q_waiting_actors: Query<(&mut Energy, &Speed), (With<Actor>, Without<TakingATurn>)>,
     q_acting_actors: Query<&Actor, (With<Energy>, With<Speed>, With<TakingATurn>)>,
 ) {
     if !q_acting_actors.is_empty() {
         return;
     }
 
-    let mut done = false;
-    while !done {
-        for (entity, mut energy, speed) in q_waiting_actors.iter_mut() {
-            energy.0 += speed.0;
+    while let Some((entity, waited)) = queue.pop() {
+        // Despawned since it was queued
+        let Ok((mut energy, speed)) = q_waiting_actors.get_mut(entity) else {
+            continue;
+        };
 
-            if energy.0 >= 100 {
-                done = true;
-                commands.entity(entity).insert(TakingATurn);
-            }
-        }
+        energy.0 += speed.0 * waited as i32;
+        commands.entity(entity).insert(TakingATurn);
+        break;
+    }
+}
+
+/// Switches between [GamePhase::PlayerTurn] and [GamePhase::MonsterTurn]
+/// whenever the turn passes to someone new.
+fn turn_phase_system(
+    q_new_turn: Query<Has<Player>, Added<TakingATurn>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+) {
+    if let Some(is_player) = q_new_turn.iter().next() {
+        next_phase.set(if is_player { GamePhase::PlayerTurn } else { GamePhase::MonsterTurn });
     }
 }
 
 fn turn_end_system(
-    mut commands: Commands, 
-    q_actors: Query<(Entity, &Energy), (With<Actor>, With<TakingATurn>)>,
+    mut commands: Commands,
+    mut queue: ResMut<TurnQueue>,
+    q_actors: Query<(Entity, &Energy, &Speed), (With<Actor>, With<TakingATurn>)>,
 ) {
-    for (entity, energy) in q_actors.iter() {
-        if energy.0 < 100 {
+    for (entity, energy, speed) in q_actors.iter() {
+        if energy.0 < TURN_ENERGY {
             commands.entity(entity).remove::<TakingATurn>();
+            queue.schedule(entity, energy.0, speed.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::Entity;
+
+    use super::TurnQueue;
+
+    #[test]
+    fn faster_actors_act_more_often() {
+        let fast = Entity::from_raw(1);
+        let slow = Entity::from_raw(2);
+        let mut queue = TurnQueue::default();
+        queue.schedule(fast, 0, 50);
+        queue.schedule(slow, 0, 25);
+
+        let mut turns = Vec::new();
+        for _ in 0..6 {
+            let (entity, _) = queue.pop().unwrap();
+            turns.push(entity);
+            queue.schedule(entity, 0, if entity == fast { 50 } else { 25 });
         }
+
+        assert_eq!(vec![fast, slow, fast, fast, slow, fast], turns);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn removed_and_still_actors_are_skipped() {
+        let a = Entity::from_raw(1);
+        let b = Entity::from_raw(2);
+        let mut queue = TurnQueue::default();
+        queue.schedule(a, 0, 20);
+        queue.schedule(b, 0, 0);
+        queue.schedule(a, 50, 20);
+
+        assert_eq!(Some((a, 3)), queue.pop());
+        assert_eq!(None, queue.pop());
+
+        queue.schedule(b, 0, 10);
+        queue.remove(b);
+        assert_eq!(None, queue.pop());
+    }
+
+    #[test]
+    fn speed_changes_keep_waiting_progress() {
+        let a = Entity::from_raw(1);
+        let b = Entity::from_raw(2);
+        let mut queue = TurnQueue::default();
+        queue.schedule(a, 0, 10);
+        queue.schedule(b, 50, 10);
+        assert_eq!(Some((b, 5)), queue.pop());
+
+        // Half way there, doubling the speed halves the rest of the wait
+        let energy = queue.gained(a);
+        assert_eq!(50, energy);
+        queue.schedule(a, energy, 20);
+        assert_eq!(Some((a, 3)), queue.pop());
+        assert_eq!(8, queue.now());
+    }
+}
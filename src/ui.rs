@@ -2,8 +2,9 @@ use bevy::prelude::*;
 use bevy_ascii_terminal::{terminal::Terminal, border::TerminalBorder, color, string::DecoratedString, StringDecorator, TerminalMeshPivot};
 use interpolation::Lerp;
 
-use crate::{UI_SIZE, VIEWPORT_SIZE, events::AttackEvent, combat::{HitPoints, MaxHitPoints}, player::Player, AppState};
+use crate::{UI_SIZE, VIEWPORT_SIZE, events::AttackEvent, combat::{HitPoints, MaxHitPoints}, experience::Experience, loot::Purse, player::Player, status::{StatusEffects, StatusKind}, AppState, GamePhase};
 use crate::map::Side;
+use crate::rng::RunSeed;
 
 pub struct UiPlugin;
 
@@ -57,7 +58,9 @@ fn handle_attacks(
 fn handle_print(
     mut print_log: ResMut<PrintLog>,
     mut q_term: Query<&mut Terminal, With<UiTerminal>>,
-    q_player: Query<(&HitPoints, &MaxHitPoints), With<Player>>,
+    q_player: Query<(&HitPoints, &MaxHitPoints, &Experience, &StatusEffects, &Purse), With<Player>>,
+    seed: Res<RunSeed>,
+    phase: Res<State<GamePhase>>,
 ) {
     if !print_log.is_changed() && q_player.is_empty() {
         warn!("Player not found for HP rendering");
@@ -75,9 +78,27 @@ fn handle_print(
 
     // Optional headers
     term.put_string([1, 0], "SYSTEM LOG:".fg(color::CYAN));
+    let seed_label = format!("SEED: {}", seed.to_code());
+    term.put_string([term.width() as i32 - seed_label.len() as i32 - 1, 0], seed_label.fg(color::GRAY));
+    // The level up prompt takes the place of the log until a stat is picked
+    if *phase.get() == GamePhase::LevelUp {
+        term.put_string([1, 1], "LEVEL UP! Choose a stat to raise:".fg(color::YELLOW));
+        term.put_string([3, 3], "[1] Max HP +10".fg(color::WHITE));
+        term.put_string([3, 4], "[2] Strength +1".fg(color::WHITE));
+        term.put_string([3, 5], "[3] Defense +1".fg(color::WHITE));
+    }
+
+    // Active status effects take the oldest log line
+    let status_y = 6;
+    let has_effects = q_player.single().is_ok_and(|(_, _, _, effects, _)| !effects.0.is_empty());
+
     // Render log messages (newest at bottom)
     let log_start_y = 1;
-    let max_lines = 6;
+    let max_lines = match (*phase.get(), has_effects) {
+        (GamePhase::LevelUp, _) => 0,
+        (_, true) => 5,
+        _ => 6,
+    };
     let log_slice = print_log.log.iter().rev().take(max_lines);
     for (i, text) in log_slice.enumerate() {
         let y = log_start_y + i as i32;
@@ -92,7 +113,7 @@ fn handle_print(
     }
 
     // Render HP bar
-    if let Ok((hp, max)) = q_player.single() {
+    if let Ok((hp, max, experience, effects, purse)) = q_player.single() {
         let hp_val = hp.0;
         let max_val = max.0;
         let bar_width = term.width() as usize - 20;
@@ -109,5 +130,28 @@ fn handle_print(
         term.put_string([label_x, 7], hp_label.fg(color::YELLOW));
         term.put_string([bar_x, 7], "█".repeat(filled_len).fg(color::RED));
         term.put_string([bar_x + filled_len as i32, 7], "□".repeat(empty_len).fg(color::DARK_GRAY));
+
+        let level_label = format!("LVL {}  XP {}/{}", experience.level, experience.xp, experience.to_next_level());
+        term.put_string([16, 0], level_label.fg(color::CYAN));
+        term.put_string([40, 0], format!("GOLD {}", purse.0).fg(color::YELLOW));
+
+        // Active status effects with the turns they have left
+        let mut x = 1;
+        for effect in effects.0.iter() {
+            let col = match effect.kind {
+                StatusKind::Poison => color::LIGHT_GREEN,
+                StatusKind::Bleed => color::RED,
+                StatusKind::Stun => color::CYAN,
+                StatusKind::Burning => color::DARK_ORANGE,
+                StatusKind::Regeneration => color::GREEN,
+            };
+            let label = format!("{}({})", effect.kind.name().to_uppercase(), effect.turns);
+            let width = label.len() as i32 + 1;
+            if x + width > term.width() as i32 {
+                break;
+            }
+            term.put_string([x, status_y], label.fg(col));
+            x += width;
+        }
     }
 }
\ No newline at end of file
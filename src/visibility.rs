@@ -1,6 +1,6 @@
 use bevy::prelude::*;
         use sark_grids::{Grid, SizedGrid};
-        use crate::{map::{Map, MapTile}, movement::Position, AppState};
+        use crate::{map::Map, movement::Position, turn_system::turns_running};
         
         use adam_fov_rs::{self, compute_fov, GridPoint};
         
@@ -13,9 +13,9 @@ use bevy::prelude::*;
         
         impl Plugin for VisibilityPlugin {
             fn build(&self, app: &mut App) {
-                app.configure_sets(Update, ViewSystemSet.run_if(in_state(AppState::WeaponSetup)))
-                    .add_systems(Update, view_system.in_set(ViewSystemSet).run_if(in_state(AppState::WeaponSetup)))
-                    .add_systems(Update, view_memory_system.after(ViewSystemSet).run_if(in_state(AppState::WeaponSetup)));
+                app.configure_sets(Update, ViewSystemSet.run_if(turns_running))
+                    .add_systems(Update, view_system.in_set(ViewSystemSet))
+                    .add_systems(Update, view_memory_system.after(ViewSystemSet).run_if(turns_running));
             }
         }
         
@@ -30,14 +30,19 @@ use bevy::prelude::*;
         
         #[allow(clippy::type_complexity)]
         fn view_system(
-            mut q_view: Query<(&mut MapView, &Position, &ViewRange), (Changed<Position>, Without<MapMemory>)>,
-            q_map: Query<&Map>,
+            mut q_view: Query<(&mut MapView, Ref<Position>, &ViewRange), Without<MapMemory>>,
+            q_map: Query<Ref<Map>>,
         ) {
             if let Ok(map) = q_map.single() {
                 let map_size = map.0.size();
                 let grid_size = [map_size.x, map_size.y];
         
                 for (mut view, pos, range) in q_view.iter_mut() {
+                    // Opening a door changes what can be seen without anyone moving
+                    if !pos.is_changed() && !map.is_changed() {
+                        continue;
+                    }
+
                     if view.0.size() != map_size {
                         view.0 = Grid::new(map_size);
                     }
@@ -50,7 +55,7 @@ use bevy::prelude::*;
                         pos.0,
                         range.0 as usize,
                         grid_size,
-                        |p| !map.0.in_bounds(p) || map.0[p] == MapTile::Wall,
+                        |p| !map.0.in_bounds(p) || map.0[p].properties().blocks_vision,
                         |p| {
                             if map.0.in_bounds(p) {
                                 view.0[p] = true;
@@ -62,14 +67,18 @@ use bevy::prelude::*;
         }
         
         fn view_memory_system(
-            mut q_view: Query<(&mut MapView, &mut MapMemory, &Position, &ViewRange), Changed<Position>>,
-            q_map: Query<&Map>,
+            mut q_view: Query<(&mut MapView, &mut MapMemory, Ref<Position>, &ViewRange)>,
+            q_map: Query<Ref<Map>>,
         ) {
             if let Ok(map) = q_map.single() {
                 let map_size = map.0.size();
                 let grid_size = [map_size.x, map_size.y];
         
                 for (mut view, mut memory, pos, range) in q_view.iter_mut() {
+                    if !pos.is_changed() && !map.is_changed() {
+                        continue;
+                    }
+
                     if view.0.size() != map_size {
                         view.0 = Grid::new(map_size);
                     }
@@ -87,7 +96,7 @@ use bevy::prelude::*;
                         pos.0,
                         range.0 as usize,
                         grid_size,
-                        |p| !map.0.in_bounds(p) || map.0[p] == MapTile::Wall,
+                        |p| !map.0.in_bounds(p) || map.0[p].properties().blocks_vision,
                         |p| {
                             if map.0.in_bounds(p) {
                                 let i = map.0.transform_lti(p);